@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::convert::TryFrom;
 
 use minijinja::value::{Kwargs, Value, ValueKind};
@@ -153,6 +154,89 @@ fn get_timezone(
     }
 }
 
+/// Formats a datetime with a format description.
+///
+/// Descriptions containing `%` are treated as strftime-style format strings,
+/// everything else is passed to the `time` crate as a version 2 description.
+fn format_datetime(datetime: OffsetDateTime, description: &str) -> Result<String, Error> {
+    let description = if description.contains('%') {
+        Cow::Owned(strftime_to_description(description)?)
+    } else {
+        Cow::Borrowed(description)
+    };
+    datetime
+        .format(
+            &format_description::parse_borrowed::<2>(&description).map_err(|err| {
+                Error::new(ErrorKind::InvalidOperation, "invalid format string").with_source(err)
+            })?,
+        )
+        .map_err(|err| {
+            Error::new(ErrorKind::InvalidOperation, "failed to format date").with_source(err)
+        })
+}
+
+/// Converts a strftime-style format string into a `time` format description.
+fn strftime_to_description(format: &str) -> Result<String, Error> {
+    let mut rv = String::with_capacity(format.len() * 2);
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '%' => {}
+            '[' | ']' | '\\' => {
+                rv.push('\\');
+                rv.push(c);
+                continue;
+            }
+            c => {
+                rv.push(c);
+                continue;
+            }
+        }
+        rv.push_str(match chars.next() {
+            Some('Y') => "[year]",
+            Some('y') => "[year repr:last_two]",
+            Some('m') => "[month]",
+            Some('b' | 'h') => "[month repr:short]",
+            Some('B') => "[month repr:long]",
+            Some('d') => "[day]",
+            Some('e') => "[day padding:space]",
+            Some('j') => "[ordinal]",
+            Some('a') => "[weekday repr:short]",
+            Some('A') => "[weekday]",
+            Some('u') => "[weekday repr:monday]",
+            Some('w') => "[weekday repr:sunday]",
+            Some('H') => "[hour]",
+            Some('I') => "[hour repr:12]",
+            Some('M') => "[minute]",
+            Some('S') => "[second]",
+            Some('f') => "[subsecond digits:6]",
+            Some('p') => "[period]",
+            Some('z') => "[offset_hour sign:mandatory][offset_minute]",
+            Some('s') => "[unix_timestamp]",
+            Some('F') => "[year]-[month]-[day]",
+            Some('D') => "[month]/[day]/[year repr:last_two]",
+            Some('T') => "[hour]:[minute]:[second]",
+            Some('R') => "[hour]:[minute]",
+            Some('n') => "\n",
+            Some('t') => "\t",
+            Some('%') => "%",
+            Some(other) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!("unsupported format specifier '%{other}'"),
+                ))
+            }
+            None => {
+                return Err(Error::new(
+                    ErrorKind::InvalidOperation,
+                    "format string ends with a lone '%'",
+                ))
+            }
+        });
+    }
+    Ok(rv)
+}
+
 /// Formats a timestamp as date and time.
 ///
 /// The value needs to be a unix timestamp, or a parsable string (ISO 8601) or a
@@ -180,7 +264,8 @@ fn get_timezone(
 /// This filter currently uses the `time` crate to format dates and uses the format
 /// string specification of that crate in version 2.  For more information read the
 /// [Format description documentation](https://time-rs.github.io/book/api/format-description.html).
-/// Additionally some special formats are supported:
+/// Format strings containing `%` are instead interpreted as strftime-style format
+/// strings (eg: `%Y-%m-%d %H:%M`).  Additionally some special formats are supported:
 ///
 /// * `short`: a short date and time format (`2023-06-24 16:37`)
 /// * `medium`: a medium length date and time format (`Jun 24 2023 16:37`)
@@ -204,26 +289,20 @@ pub fn datetimeformat(state: &State, value: Value, kwargs: Kwargs) -> Result<Str
     });
     kwargs.assert_all_used()?;
 
-    datetime
-        .format(
-            &format_description::parse_borrowed::<2>(match format {
-                "short" => "[year]-[month]-[day] [hour]:[minute]",
-                "medium" => "[month repr:short] [day padding:none] [year] [hour]:[minute]",
-                "long" => "[month repr:long] [day padding:none] [year] [hour]:[minute]:[second]",
-                "full" => "[weekday], [month repr:long] [day padding:none] [year] [hour]:[minute]:[second].[subsecond]",
-                "iso" => {
-                    "[year]-[month]-[day]T[hour]:[minute]:[second][offset_hour sign:mandatory]:[offset_minute]"
-                }
-                "unix" => "[unix_timestamp]",
-                other => other,
-            })
-            .map_err(|err| {
-                Error::new(ErrorKind::InvalidOperation, "invalid format string").with_source(err)
-            })?,
-        )
-        .map_err(|err| {
-            Error::new(ErrorKind::InvalidOperation, "failed to format date").with_source(err)
-        })
+    format_datetime(
+        datetime,
+        match format {
+            "short" => "[year]-[month]-[day] [hour]:[minute]",
+            "medium" => "[month repr:short] [day padding:none] [year] [hour]:[minute]",
+            "long" => "[month repr:long] [day padding:none] [year] [hour]:[minute]:[second]",
+            "full" => "[weekday], [month repr:long] [day padding:none] [year] [hour]:[minute]:[second].[subsecond]",
+            "iso" => {
+                "[year]-[month]-[day]T[hour]:[minute]:[second][offset_hour sign:mandatory]:[offset_minute]"
+            }
+            "unix" => "[unix_timestamp]",
+            other => other,
+        },
+    )
 }
 
 /// Formats a timestamp as time.
@@ -253,7 +332,8 @@ pub fn datetimeformat(state: &State, value: Value, kwargs: Kwargs) -> Result<Str
 /// This filter currently uses the `time` crate to format dates and uses the format
 /// string specification of that crate in version 2.  For more information read the
 /// [Format description documentation](https://time-rs.github.io/book/api/format-description.html).
-/// Additionally some special formats are supported:
+/// Format strings containing `%` are instead interpreted as strftime-style format
+/// strings (eg: `%Y-%m-%d %H:%M`).  Additionally some special formats are supported:
 ///
 /// * `short` and `medium`: hour and minute (`16:37`)
 /// * `long`: includes seconds too (`16:37:22`)
@@ -276,25 +356,19 @@ pub fn timeformat(state: &State, value: Value, kwargs: Kwargs) -> Result<String,
     });
     kwargs.assert_all_used()?;
 
-    datetime
-        .format(
-            &format_description::parse_borrowed::<2>(match format {
-                "short" | "medium" => "[hour]:[minute]",
-                "long" => "[hour]:[minute]:[second]",
-                "full" => "[hour]:[minute]:[second].[subsecond]",
-                "iso" => {
-                    "[year]-[month]-[day]T[hour]:[minute]:[second][offset_hour sign:mandatory]:[offset_minute]"
-                }
-                "unix" => "[unix_timestamp]",
-                other => other,
-            })
-            .map_err(|err| {
-                Error::new(ErrorKind::InvalidOperation, "invalid format string").with_source(err)
-            })?,
-        )
-        .map_err(|err| {
-            Error::new(ErrorKind::InvalidOperation, "failed to format date").with_source(err)
-        })
+    format_datetime(
+        datetime,
+        match format {
+            "short" | "medium" => "[hour]:[minute]",
+            "long" => "[hour]:[minute]:[second]",
+            "full" => "[hour]:[minute]:[second].[subsecond]",
+            "iso" => {
+                "[year]-[month]-[day]T[hour]:[minute]:[second][offset_hour sign:mandatory]:[offset_minute]"
+            }
+            "unix" => "[unix_timestamp]",
+            other => other,
+        },
+    )
 }
 
 /// Formats a timestamp as date.
@@ -325,7 +399,8 @@ pub fn timeformat(state: &State, value: Value, kwargs: Kwargs) -> Result<String,
 /// This filter currently uses the `time` crate to format dates and uses the format
 /// string specification of that crate in version 2.  For more information read the
 /// [Format description documentation](https://time-rs.github.io/book/api/format-description.html).
-/// Additionally some special formats are supported:
+/// Format strings containing `%` are instead interpreted as strftime-style format
+/// strings (eg: `%Y-%m-%d %H:%M`).  Additionally some special formats are supported:
 ///
 /// * `short`: a short date format (`2023-06-24`)
 /// * `medium`: a medium length date format (`Jun 24 2023`)
@@ -347,20 +422,14 @@ pub fn dateformat(state: &State, value: Value, kwargs: Kwargs) -> Result<String,
     });
     kwargs.assert_all_used()?;
 
-    datetime
-        .format(
-            &format_description::parse_borrowed::<2>(match format {
-                "short" => "[year]-[month]-[day]",
-                "medium" => "[month repr:short] [day padding:none] [year]",
-                "long" => "[month repr:long] [day padding:none] [year]",
-                "full" => "[weekday], [month repr:long] [day padding:none] [year]",
-                other => other,
-            })
-            .map_err(|err| {
-                Error::new(ErrorKind::InvalidOperation, "invalid format string").with_source(err)
-            })?,
-        )
-        .map_err(|err| {
-            Error::new(ErrorKind::InvalidOperation, "failed to format date").with_source(err)
-        })
+    format_datetime(
+        datetime,
+        match format {
+            "short" => "[year]-[month]-[day]",
+            "medium" => "[month repr:short] [day padding:none] [year]",
+            "long" => "[month repr:long] [day padding:none] [year]",
+            "full" => "[weekday], [month repr:long] [day padding:none] [year]",
+            other => other,
+        },
+    )
 }
@@ -249,3 +249,44 @@ fn test_timeformat() {
         .unwrap();
     assert_eq!(expr.eval(()).unwrap().to_string(), "19:37");
 }
+
+#[test]
+fn test_strftime_formats() {
+    let mut env = minijinja::Environment::new();
+    minijinja_contrib::add_to_environment(&mut env);
+
+    let expr = env
+        .compile_expression("value|datetimeformat(format=format)")
+        .unwrap();
+    assert_eq!(
+        expr.eval(context!(value => 1687624642, format => "%Y-%m-%d %H:%M:%S"))
+            .unwrap()
+            .to_string(),
+        "2023-06-24 16:37:22"
+    );
+    assert_eq!(
+        expr.eval(context!(
+            value => "2023-06-24T16:37:22+02:00",
+            format => "%a, %d %b %Y [%I:%M %p] %z %%"
+        ))
+        .unwrap()
+        .to_string(),
+        "Sat, 24 Jun 2023 [04:37 PM] +0200 %"
+    );
+    assert_eq!(
+        expr.eval(context!(value => 1687624642, format => "%H:%M %Z"))
+            .unwrap_err()
+            .detail(),
+        Some("unsupported format specifier '%Z'")
+    );
+
+    let expr = env
+        .compile_expression("1687624642|dateformat(format='%F', tz='Europe/Moscow')")
+        .unwrap();
+    assert_eq!(expr.eval(()).unwrap().to_string(), "2023-06-24");
+
+    let expr = env
+        .compile_expression("1687624642|timeformat(format='%T', tz='Europe/Moscow')")
+        .unwrap();
+    assert_eq!(expr.eval(()).unwrap().to_string(), "19:37:22");
+}
@@ -1,4 +1,5 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
 
 use crate::compiler::ast;
 use crate::compiler::instructions::{
@@ -48,6 +49,7 @@ pub struct CodeGenerator<'source> {
     span_stack: Vec<Span>,
     filter_local_ids: BTreeMap<&'source str, LocalId>,
     test_local_ids: BTreeMap<&'source str, LocalId>,
+    streaming_filters: Arc<BTreeSet<String>>,
     raw_template_bytes: usize,
 }
 
@@ -62,10 +64,16 @@ impl<'source> CodeGenerator<'source> {
             span_stack: Vec::with_capacity(32),
             filter_local_ids: BTreeMap::new(),
             test_local_ids: BTreeMap::new(),
+            streaming_filters: Arc::default(),
             raw_template_bytes: 0,
         }
     }
 
+    /// Sets the names of the filters that write directly into the output.
+    pub fn set_streaming_filters(&mut self, names: Arc<BTreeSet<String>>) {
+        self.streaming_filters = names;
+    }
+
     /// Sets the current location's line.
     pub fn set_line(&mut self, lineno: u32) {
         self.current_line = lineno;
@@ -112,6 +120,7 @@ impl<'source> CodeGenerator<'source> {
     fn new_subgenerator(&self) -> CodeGenerator<'source> {
         let mut sub = CodeGenerator::new(self.instructions.name(), self.instructions.source());
         sub.current_line = self.current_line;
+        sub.streaming_filters = self.streaming_filters.clone();
         sub.span_stack = self.span_stack.last().copied().into_iter().collect();
         sub
     }
@@ -254,6 +263,7 @@ impl<'source> CodeGenerator<'source> {
                 self.add(Instruction::PushWith);
                 for (target, expr) in &with_block.assignments {
                     self.compile_expr(expr);
+                    self.add(Instruction::Snapshot);
                     self.compile_assignment(target);
                 }
                 for node in &with_block.body {
@@ -470,6 +480,22 @@ impl<'source> CodeGenerator<'source> {
                 }
                 _ => {}
             }
+        } else if let ast::Expr::Filter(f) = &expr.expr {
+            if let (Some(ref filter_expr), true) =
+                (&f.expr, self.streaming_filters.contains(f.name))
+            {
+                // streaming filters applied directly in print statements
+                // can write into the output without an intermediate value.
+                self.push_span(f.span());
+                self.compile_expr(filter_expr);
+                for arg in &f.args {
+                    self.compile_expr(arg);
+                }
+                let local_id = get_local_id(&mut self.filter_local_ids, f.name);
+                self.add(Instruction::EmitFilter(f.name, f.args.len() + 1, local_id));
+                self.pop_span();
+                return;
+            }
         }
         self.compile_expr(&expr.expr);
         self.add(Instruction::Emit);
@@ -517,6 +543,12 @@ impl<'source> CodeGenerator<'source> {
             ast::Expr::Var(var) => {
                 self.add(Instruction::StoreLocal(var.id));
             }
+            ast::Expr::GetAttr(attr) => {
+                self.push_span(attr.span());
+                self.compile_expr(&attr.expr);
+                self.add(Instruction::SetAttr(attr.name));
+                self.pop_span();
+            }
             ast::Expr::List(list) => {
                 self.push_span(list.span());
                 self.add(Instruction::UnpackList(list.items.len()));
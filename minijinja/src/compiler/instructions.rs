@@ -36,6 +36,9 @@ pub enum Instruction<'source> {
     /// Stores a variable (only possible in for loops)
     StoreLocal(&'source str),
 
+    /// Assigns an attribute on a namespace.
+    SetAttr(&'source str),
+
     /// Load a variable,
     Lookup(&'source str),
 
@@ -124,6 +127,9 @@ pub enum Instruction<'source> {
     /// Apply a filter.
     ApplyFilter(&'source str, usize, LocalId),
 
+    /// Apply a filter and emit the result.
+    EmitFilter(&'source str, usize, LocalId),
+
     /// Perform a filter.
     PerformTest(&'source str, usize, LocalId),
 
@@ -138,6 +144,9 @@ pub enum Instruction<'source> {
     /// Starts a with block.
     PushWith,
 
+    /// Replaces the stack top with a snapshot of it for a new scope.
+    Snapshot,
+
     /// Does a single loop iteration
     ///
     /// The argument is the jump target for when the loop
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use crate::compiler::tokens::{Span, Token};
 use crate::error::{Error, ErrorKind};
 use crate::utils::{memchr, memstr, unescape};
@@ -10,10 +12,28 @@ pub use crate::custom_syntax::SyntaxConfig;
 #[derive(Debug, Clone, Default)]
 pub struct SyntaxConfig;
 
+/// Tokenizer settings that are independent of the delimiters.
+#[derive(Debug, Clone, Default)]
+pub struct LexerConfig {
+    /// Emits comments as template data.
+    pub keep_comments: bool,
+    /// Prefix that turns a line into a block statement.
+    pub line_statement_prefix: Option<Arc<str>>,
+    /// Prefix that starts a comment that runs until the end of the line.
+    pub line_comment_prefix: Option<Arc<str>>,
+}
+
+impl LexerConfig {
+    fn has_line_markers(&self) -> bool {
+        self.line_statement_prefix.is_some() || self.line_comment_prefix.is_some()
+    }
+}
+
 enum LexerState {
     Template,
     InVariable,
     InBlock,
+    InLineStatement,
 }
 
 /// Utility enum that defines a marker.
@@ -102,6 +122,90 @@ fn match_start_marker(rest: &str, syntax_config: &SyntaxConfig) -> Option<(Start
     }
 }
 
+/// Utility enum that defines a line based marker.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum LineMarker {
+    Statement,
+    Comment,
+}
+
+fn is_line_start(input: &str, offset: u32) -> bool {
+    offset == 0 || input.as_bytes().get(offset as usize - 1) == Some(&b'\n')
+}
+
+/// Checks if a line statement or line comment starts at the beginning of `rest`.
+///
+/// Leading spaces and tabs are skipped and line statements are only recognized
+/// at the start of a line.  If both prefixes match, the longer one wins.  Returns
+/// the marker and the number of bytes up to and including the prefix.
+fn match_line_marker(
+    rest: &str,
+    at_line_start: bool,
+    config: &LexerConfig,
+) -> Option<(LineMarker, usize)> {
+    if !config.has_line_markers() {
+        return None;
+    }
+    let trimmed = rest.trim_start_matches([' ', '\t']);
+    let ws = rest.len() - trimmed.len();
+    let statement = config
+        .line_statement_prefix
+        .as_deref()
+        .filter(|prefix| at_line_start && trimmed.starts_with(prefix))
+        .map(|prefix| (LineMarker::Statement, prefix.len()));
+    let comment = config
+        .line_comment_prefix
+        .as_deref()
+        .filter(|prefix| trimmed.starts_with(prefix))
+        .map(|prefix| (LineMarker::Comment, prefix.len()));
+    match (statement, comment) {
+        (Some(a), Some(b)) => Some(if b.1 >= a.1 { b } else { a }),
+        (a, b) => a.or(b),
+    }
+    .map(|(marker, len)| (marker, ws + len))
+}
+
+/// Finds the next line statement or line comment before `limit`.
+///
+/// The returned offset points to the whitespace that precedes the prefix
+/// so that [`match_line_marker`] matches at that position.
+fn find_line_marker(
+    rest: &str,
+    at_line_start: bool,
+    limit: usize,
+    config: &LexerConfig,
+) -> Option<usize> {
+    let haystack = &rest.as_bytes()[..limit];
+    let mut rv = None;
+    if let Some(ref prefix) = config.line_comment_prefix {
+        if let Some(idx) = memstr(haystack, prefix.as_bytes()) {
+            rv = Some(rest[..idx].trim_end_matches([' ', '\t']).len());
+        }
+    }
+    if config.line_statement_prefix.is_some() {
+        let end = rv.unwrap_or(limit);
+        let mut ptr = if at_line_start {
+            0
+        } else {
+            match memchr(&haystack[..end], b'\n') {
+                Some(idx) => idx + 1,
+                None => return rv,
+            }
+        };
+        while ptr < end {
+            if let Some((LineMarker::Statement, _)) = match_line_marker(&rest[ptr..], true, config)
+            {
+                return Some(ptr);
+            }
+            match memchr(&haystack[ptr..end], b'\n') {
+                Some(idx) => ptr += idx + 1,
+                None => break,
+            }
+        }
+    }
+    rv
+}
+
 fn match_start_marker_default(rest: &str) -> Option<(StartMarker, usize)> {
     match rest.get(..2) {
         Some("{{") => Some((StartMarker::Variable, 2)),
@@ -215,7 +319,7 @@ impl<'s> TokenizerState<'s> {
         Error::new(ErrorKind::SyntaxError, msg)
     }
 
-    fn eat_number(&mut self) -> Result<(Token<'s>, Span), Error> {
+    fn eat_number(&mut self, int_only: bool) -> Result<(Token<'s>, Span), Error> {
         #[derive(Copy, Clone)]
         enum State {
             Integer,      // 123
@@ -232,7 +336,13 @@ impl<'s> TokenizerState<'s> {
             .iter()
             .take_while(|&c| c.is_ascii_digit())
             .count();
-        for c in self.rest.as_bytes()[num_len..].iter().copied() {
+        // after a dot (`foo.0.1`) only integers are accepted
+        let tail = if int_only {
+            &[][..]
+        } else {
+            &self.rest.as_bytes()[num_len..]
+        };
+        for c in tail.iter().copied() {
             state = match (c, state) {
                 (b'.', State::Integer) => State::Fraction,
                 (b'E' | b'e', State::Integer | State::Fraction) => State::Exponent,
@@ -326,10 +436,21 @@ impl<'s> TokenizerState<'s> {
 }
 
 /// Tokenizes the source.
+#[cfg(any(test, feature = "unstable_machinery"))]
 pub fn tokenize(
     input: &str,
     in_expr: bool,
     syntax_config: SyntaxConfig,
+) -> impl Iterator<Item = Result<(Token<'_>, Span), Error>> {
+    tokenize_ext(input, in_expr, syntax_config, LexerConfig::default())
+}
+
+/// Tokenizes the source with additional tokenizer settings.
+pub(crate) fn tokenize_ext(
+    input: &str,
+    in_expr: bool,
+    syntax_config: SyntaxConfig,
+    lexer_config: LexerConfig,
 ) -> impl Iterator<Item = Result<(Token<'_>, Span), Error>> {
     let mut state = TokenizerState {
         rest: input,
@@ -344,6 +465,7 @@ pub fn tokenize(
         current_offset: 0,
     };
     let mut trim_leading_whitespace = false;
+    let mut line_statement_depth = 0usize;
 
     std::iter::from_fn(move || {
         let (variable_end, block_start, block_end, comment_end) = {
@@ -365,12 +487,40 @@ pub fn tokenize(
 
         loop {
             if state.rest.is_empty() || state.failed {
+                // line statements can also be terminated by the end of the input
+                if !state.failed && matches!(state.stack.last(), Some(LexerState::InLineStatement))
+                {
+                    state.stack.pop();
+                    return Some(Ok((Token::BlockEnd, state.span(state.loc()))));
+                }
                 return None;
             }
 
             let mut old_loc = state.loc();
             match state.stack.last() {
                 Some(LexerState::Template) => {
+                    let at_line_start = is_line_start(input, state.current_offset);
+                    match match_line_marker(state.rest, at_line_start, &lexer_config) {
+                        Some((LineMarker::Statement, skip)) => {
+                            state.advance(skip);
+                            line_statement_depth = 0;
+                            state.stack.push(LexerState::InLineStatement);
+                            return Some(Ok((Token::BlockStart, state.span(old_loc))));
+                        }
+                        Some((LineMarker::Comment, _)) => {
+                            let end =
+                                memchr(state.rest.as_bytes(), b'\n').unwrap_or(state.rest.len());
+                            let comment = state.advance(end).trim_start_matches([' ', '\t']);
+                            if lexer_config.keep_comments {
+                                return Some(Ok((
+                                    Token::TemplateData(comment),
+                                    state.span(old_loc),
+                                )));
+                            }
+                            continue;
+                        }
+                        None => {}
+                    }
                     match match_start_marker(state.rest, &syntax_config) {
                         Some((StartMarker::Comment, skip)) => {
                             if let Some(end) =
@@ -385,7 +535,13 @@ pub fn tokenize(
                                 {
                                     trim_leading_whitespace = true;
                                 }
-                                state.advance(end + skip + comment_end.len());
+                                let comment = state.advance(end + skip + comment_end.len());
+                                if lexer_config.keep_comments {
+                                    return Some(Ok((
+                                        Token::TemplateData(comment),
+                                        state.span(old_loc),
+                                    )));
+                                }
                                 continue;
                             } else {
                                 return Some(Err(state.syntax_error("unexpected end of comment")));
@@ -455,9 +611,22 @@ pub fn tokenize(
                     }
                     old_loc = state.loc();
 
-                    let (lead, span) = match find_start_marker(state.rest, &syntax_config) {
-                        Some((start, false)) => (state.advance(start), state.span(old_loc)),
-                        Some((start, _)) => {
+                    let start_marker = find_start_marker(state.rest, &syntax_config);
+                    let line_marker = if lexer_config.has_line_markers() {
+                        find_line_marker(
+                            state.rest,
+                            is_line_start(input, state.current_offset),
+                            start_marker.map_or(state.rest.len(), |x| x.0),
+                            &lexer_config,
+                        )
+                    } else {
+                        None
+                    };
+                    let (lead, span) = match (line_marker, start_marker) {
+                        (Some(start), _) | (None, Some((start, false))) => {
+                            (state.advance(start), state.span(old_loc))
+                        }
+                        (None, Some((start, _))) => {
                             let peeked = &state.rest[..start];
                             let trimmed = peeked.trim_end();
                             let lead = state.advance(trimmed.len());
@@ -465,21 +634,25 @@ pub fn tokenize(
                             state.advance(peeked.len() - trimmed.len());
                             (lead, span)
                         }
-                        None => (state.advance(state.rest.len()), state.span(old_loc)),
+                        (None, None) => (state.advance(state.rest.len()), state.span(old_loc)),
                     };
                     if lead.is_empty() {
                         continue;
                     }
                     return Some(Ok((Token::TemplateData(lead), span)));
                 }
-                Some(LexerState::InBlock | LexerState::InVariable) => {
+                Some(
+                    LexerState::InBlock | LexerState::InVariable | LexerState::InLineStatement,
+                ) => {
+                    // line statements end with the line unless brackets are open
+                    let at_line_statement_end =
+                        matches!(state.stack.last(), Some(LexerState::InLineStatement))
+                            && line_statement_depth == 0;
+
                     // in blocks whitespace is generally ignored, skip it.
-                    match state
-                        .rest
-                        .as_bytes()
-                        .iter()
-                        .position(|&x| !x.is_ascii_whitespace())
-                    {
+                    match state.rest.as_bytes().iter().position(|&x| {
+                        !x.is_ascii_whitespace() || (at_line_statement_end && x == b'\n')
+                    }) {
                         Some(0) => {}
                         None => {
                             state.advance(state.rest.len());
@@ -491,6 +664,35 @@ pub fn tokenize(
                         }
                     }
 
+                    if at_line_statement_end {
+                        if state.rest.starts_with('\n') {
+                            state.stack.pop();
+                            state.advance(1);
+                            return Some(Ok((Token::BlockEnd, state.span(old_loc))));
+                        }
+                        // a trailing colon is permitted: `# for item in seq:`
+                        if state.rest.starts_with(':')
+                            && state.rest[1..]
+                                .trim_start_matches([' ', '\t', '\r'])
+                                .chars()
+                                .next()
+                                .map_or(true, |c| c == '\n')
+                        {
+                            state.advance(1);
+                            continue;
+                        }
+                    }
+                    if matches!(state.stack.last(), Some(LexerState::InLineStatement)) {
+                        if let Some((LineMarker::Comment, _)) =
+                            match_line_marker(state.rest, false, &lexer_config)
+                        {
+                            let end =
+                                memchr(state.rest.as_bytes(), b'\n').unwrap_or(state.rest.len());
+                            state.advance(end);
+                            continue;
+                        }
+                    }
+
                     // look out for the end of blocks
                     if let Some(&LexerState::InBlock) = state.stack.last() {
                         if state.rest.get(..1) == Some("-")
@@ -506,7 +708,7 @@ pub fn tokenize(
                             state.advance(block_end.len());
                             return Some(Ok((Token::BlockEnd, state.span(old_loc))));
                         }
-                    } else {
+                    } else if let Some(&LexerState::InVariable) = state.stack.last() {
                         if state.rest.get(..1) == Some("-")
                             && state.rest.get(1..variable_end.len() + 1) == Some(variable_end)
                         {
@@ -565,10 +767,22 @@ pub fn tokenize(
                         Some(b'"') => {
                             return Some(state.eat_string(b'"'));
                         }
-                        Some(c) if c.is_ascii_digit() => return Some(state.eat_number()),
+                        Some(c) if c.is_ascii_digit() => {
+                            let after_dot = input[..state.current_offset as usize].ends_with('.');
+                            return Some(state.eat_number(after_dot));
+                        }
                         _ => None,
                     };
                     if let Some(op) = op {
+                        match op {
+                            Token::ParenOpen | Token::BracketOpen | Token::BraceOpen => {
+                                line_statement_depth += 1;
+                            }
+                            Token::ParenClose | Token::BracketClose | Token::BraceClose => {
+                                line_statement_depth = line_statement_depth.saturating_sub(1);
+                            }
+                            _ => {}
+                        }
                         state.advance(1);
                         return Some(Ok((op, state.span(old_loc))));
                     }
@@ -124,6 +124,7 @@ fn tracker_visit_expr<'a>(expr: &ast::Expr<'a>, state: &mut AssignmentTracker<'a
                                 state.assign_nested(rv);
                                 return;
                             }
+                            break;
                         }
                         ast::Expr::GetAttr(expr) => {
                             attrs.push(expr.name);
@@ -164,6 +165,7 @@ fn tracker_visit_expr<'a>(expr: &ast::Expr<'a>, state: &mut AssignmentTracker<'a
 fn track_assign<'a>(expr: &ast::Expr<'a>, state: &mut AssignmentTracker<'a>) {
     match expr {
         ast::Expr::Var(var) => state.assign(var.id),
+        ast::Expr::GetAttr(attr) => tracker_visit_expr(&attr.expr, state),
         ast::Expr::List(list) => list.items.iter().for_each(|x| track_assign(x, state)),
         _ => {}
     }
@@ -247,9 +249,17 @@ fn track_walk<'a>(node: &ast::Stmt<'a>, state: &mut AssignmentTracker<'a>) {
         #[cfg(feature = "macros")]
         ast::Stmt::Macro(stmt) => {
             state.assign(stmt.name);
+            track_macro(stmt, state);
         }
         #[cfg(feature = "macros")]
-        ast::Stmt::CallBlock(_) => {}
+        ast::Stmt::CallBlock(stmt) => {
+            tracker_visit_expr(&stmt.call.expr, state);
+            stmt.call
+                .args
+                .iter()
+                .for_each(|x| tracker_visit_expr(x, state));
+            track_macro(&stmt.macro_decl, state);
+        }
         ast::Stmt::Do(stmt) => {
             tracker_visit_expr(&stmt.call.expr, state);
             stmt.call
@@ -259,3 +269,13 @@ fn track_walk<'a>(node: &ast::Stmt<'a>, state: &mut AssignmentTracker<'a>) {
         }
     }
 }
+
+#[cfg(feature = "macros")]
+fn track_macro<'a>(m: &ast::Macro<'a>, state: &mut AssignmentTracker<'a>) {
+    m.defaults.iter().for_each(|x| tracker_visit_expr(x, state));
+    state.push();
+    state.assign("caller");
+    m.args.iter().for_each(|arg| track_assign(arg, state));
+    m.body.iter().for_each(|node| track_walk(node, state));
+    state.pop();
+}
@@ -3,7 +3,7 @@ use std::collections::BTreeSet;
 use std::fmt;
 
 use crate::compiler::ast::{self, Spanned};
-use crate::compiler::lexer::{tokenize, SyntaxConfig};
+use crate::compiler::lexer::{tokenize_ext, LexerConfig, SyntaxConfig};
 use crate::compiler::tokens::{Span, Token};
 use crate::error::{Error, ErrorKind};
 use crate::value::Value;
@@ -100,9 +100,14 @@ struct TokenStream<'a> {
 
 impl<'a> TokenStream<'a> {
     /// Tokenize a template
-    pub fn new(source: &'a str, in_expr: bool, syntax_config: SyntaxConfig) -> TokenStream<'a> {
-        let mut iter =
-            Box::new(tokenize(source, in_expr, syntax_config)) as Box<dyn Iterator<Item = _>>;
+    pub fn new(
+        source: &'a str,
+        in_expr: bool,
+        syntax_config: SyntaxConfig,
+        lexer_config: LexerConfig,
+    ) -> TokenStream<'a> {
+        let mut iter = Box::new(tokenize_ext(source, in_expr, syntax_config, lexer_config))
+            as Box<dyn Iterator<Item = _>>;
         let current = iter.next();
         TokenStream {
             iter,
@@ -222,9 +227,14 @@ macro_rules! with_recursion_guard {
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(source: &'a str, in_expr: bool, syntax_config: SyntaxConfig) -> Parser<'a> {
+    pub fn new(
+        source: &'a str,
+        in_expr: bool,
+        syntax_config: SyntaxConfig,
+        lexer_config: LexerConfig,
+    ) -> Parser<'a> {
         Parser {
-            stream: TokenStream::new(source, in_expr, syntax_config),
+            stream: TokenStream::new(source, in_expr, syntax_config, lexer_config),
             in_macro: false,
             blocks: BTreeSet::new(),
             depth: 0,
@@ -352,11 +362,25 @@ impl<'a> Parser<'a> {
             match ok!(self.stream.current()) {
                 Some((Token::Dot, _)) => {
                     ok!(self.stream.next());
-                    let (name, _) = expect_token!(self, Token::Ident(name) => name, "identifier");
-                    expr = ast::Expr::GetAttr(Spanned::new(
-                        ast::GetAttr { name, expr },
-                        self.stream.expand_span(span),
-                    ));
+                    // `foo.0` is item access by integer like `foo[0]`
+                    if let Some((Token::Int(idx), idx_span)) = ok!(self.stream.current()) {
+                        let subscript_expr = make_const(Value::from(*idx), idx_span);
+                        ok!(self.stream.next());
+                        expr = ast::Expr::GetItem(Spanned::new(
+                            ast::GetItem {
+                                expr,
+                                subscript_expr,
+                            },
+                            self.stream.expand_span(span),
+                        ));
+                    } else {
+                        let (name, _) =
+                            expect_token!(self, Token::Ident(name) => name, "identifier");
+                        expr = ast::Expr::GetAttr(Spanned::new(
+                            ast::GetAttr { name, expr },
+                            self.stream.expand_span(span),
+                        ));
+                    }
                 }
                 Some((Token::BracketOpen, _)) => {
                     ok!(self.stream.next());
@@ -809,7 +833,18 @@ impl<'a> Parser<'a> {
             expect_token!(self, Token::ParenClose, "`)`");
             (assign, true)
         } else {
-            (ok!(self.parse_assign_name()), false)
+            let span = self.stream.current_span();
+            let mut target = ok!(self.parse_assign_name());
+            // assignments to attributes are only supported on namespaces which
+            // the engine checks at runtime.
+            if skip_token!(self, Token::Dot) {
+                let (name, _) = expect_token!(self, Token::Ident(name) => name, "identifier");
+                target = ast::Expr::GetAttr(Spanned::new(
+                    ast::GetAttr { name, expr: target },
+                    self.stream.expand_span(span),
+                ));
+            }
+            (target, false)
         };
 
         if !in_paren && matches_token!(self, Token::BlockEnd | Token::Pipe) {
@@ -827,9 +862,63 @@ impl<'a> Parser<'a> {
                 body,
             }))
         } else {
-            expect_token!(self, Token::Assign, "assignment operator");
-            let expr = ok!(self.parse_expr());
-            Ok(SetParseResult::Set(ast::Set { target, expr }))
+            let augmented_op = match ok!(self.stream.current()) {
+                Some((Token::Plus, _)) => Some(ast::BinOpKind::Add),
+                Some((Token::Minus, _)) => Some(ast::BinOpKind::Sub),
+                Some((Token::Mul, _)) => Some(ast::BinOpKind::Mul),
+                Some((Token::Div, _)) => Some(ast::BinOpKind::Div),
+                _ => None,
+            };
+            let op = match augmented_op {
+                Some(op) => op,
+                None => {
+                    expect_token!(self, Token::Assign, "assignment operator");
+                    let expr = ok!(self.parse_expr());
+                    return Ok(SetParseResult::Set(ast::Set { target, expr }));
+                }
+            };
+
+            // augmented assignments (`+=` etc.) are desugared into a regular
+            // assignment of a binary operation on the target.
+            let var = match target {
+                ast::Expr::Var(ref var) if !in_paren => {
+                    ast::Expr::Var(Spanned::new(ast::Var { id: var.id }, var.span()))
+                }
+                ast::Expr::GetAttr(ref attr) => match attr.expr {
+                    ast::Expr::Var(ref var) => ast::Expr::GetAttr(Spanned::new(
+                        ast::GetAttr {
+                            name: attr.name,
+                            expr: ast::Expr::Var(Spanned::new(ast::Var { id: var.id }, var.span())),
+                        },
+                        attr.span(),
+                    )),
+                    _ => unreachable!(),
+                },
+                _ => {
+                    syntax_error!("augmented assignment requires a variable or namespace attribute")
+                }
+            };
+            ok!(self.stream.next());
+            let op_end = self.stream.last_span().end_offset;
+            if !matches_token!(self, Token::Assign)
+                || self.stream.current_span().start_offset != op_end
+            {
+                syntax_error!("expected assignment operator");
+            }
+            ok!(self.stream.next());
+            let span = self.stream.current_span();
+            let right = ok!(self.parse_expr());
+            Ok(SetParseResult::Set(ast::Set {
+                target,
+                expr: ast::Expr::BinOp(Spanned::new(
+                    ast::BinOp {
+                        op,
+                        left: var,
+                        right,
+                    },
+                    self.stream.expand_span(span),
+                )),
+            }))
         }
     }
 
@@ -843,6 +932,12 @@ impl<'a> Parser<'a> {
             syntax_error!("block '{}' defined twice", name);
         }
 
+        // blocks always see the enclosing scope, `scoped` is accepted for
+        // compatibility with Jinja2 but has no effect.
+        if matches_token!(self, Token::Ident("scoped")) {
+            ok!(self.stream.next());
+        }
+
         expect_token!(self, Token::BlockEnd, "end of block");
         let body = ok!(self.subparse(&|tok| matches!(tok, Token::Ident("endblock"))));
         ok!(self.stream.next());
@@ -1109,7 +1204,13 @@ impl<'a> Parser<'a> {
 /// Parses a template
 #[cfg(feature = "unstable_machinery")]
 pub fn parse<'source>(source: &'source str, filename: &str) -> Result<ast::Stmt<'source>, Error> {
-    parse_with_syntax(source, filename, Default::default(), false)
+    parse_with_syntax(
+        source,
+        filename,
+        Default::default(),
+        false,
+        Default::default(),
+    )
 }
 
 /// Parses a template with a specific syntax
@@ -1118,6 +1219,7 @@ pub fn parse_with_syntax<'source>(
     filename: &str,
     syntax_config: SyntaxConfig,
     keep_trailing_newline: bool,
+    lexer_config: LexerConfig,
 ) -> Result<ast::Stmt<'source>, Error> {
     // we want to chop off a single newline at the end.  This means that a template
     // by default does not end in a newline which is a useful property to allow
@@ -1133,7 +1235,7 @@ pub fn parse_with_syntax<'source>(
         }
     }
 
-    let mut parser = Parser::new(source, false, syntax_config);
+    let mut parser = Parser::new(source, false, syntax_config, lexer_config);
     parser.parse().map_err(|mut err| {
         if err.line().is_none() {
             err.set_filename_and_span(filename, parser.stream.last_span())
@@ -1144,7 +1246,7 @@ pub fn parse_with_syntax<'source>(
 
 /// Parses an expression
 pub fn parse_expr(source: &str, syntax_config: SyntaxConfig) -> Result<ast::Expr<'_>, Error> {
-    let mut parser = Parser::new(source, true, syntax_config);
+    let mut parser = Parser::new(source, true, syntax_config, LexerConfig::default());
     parser
         .parse_expr()
         .and_then(|result| {
@@ -94,16 +94,52 @@ impl Syntax {
         }))
     }
 
-    /// block, variable and comment start strings must be different
+    /// Validates the delimiters.
+    ///
+    /// All delimiters must be non empty and none of the block, variable and
+    /// comment start strings may be a prefix of another one (eg: `{%` and
+    /// `{%%`) as the lexer could not tell them apart.
     fn check_delimiters(&self) -> Result<(), Error> {
-        if self.block_start != self.variable_start
-            && self.block_start != self.comment_start
-            && self.variable_start != self.comment_start
-        {
-            Ok(())
-        } else {
-            Err(ErrorKind::InvalidDelimiter.into())
+        for (name, delimiter) in [
+            ("block_start", &self.block_start),
+            ("block_end", &self.block_end),
+            ("variable_start", &self.variable_start),
+            ("variable_end", &self.variable_end),
+            ("comment_start", &self.comment_start),
+            ("comment_end", &self.comment_end),
+        ] {
+            if delimiter.is_empty() {
+                return Err(Error::new(
+                    ErrorKind::InvalidDelimiter,
+                    format!("{name} delimiter must not be empty"),
+                ));
+            }
         }
+
+        let starts = [
+            ("block_start", &self.block_start),
+            ("variable_start", &self.variable_start),
+            ("comment_start", &self.comment_start),
+        ];
+        for (idx, (a_name, a)) in starts.iter().enumerate() {
+            for (b_name, b) in &starts[idx + 1..] {
+                if a == b {
+                    return Err(Error::new(
+                        ErrorKind::InvalidDelimiter,
+                        format!("{a_name} and {b_name} delimiters must be different"),
+                    ));
+                } else if a.starts_with(b as &str) || b.starts_with(a as &str) {
+                    return Err(Error::new(
+                        ErrorKind::InvalidDelimiter,
+                        format!(
+                            "{a_name} and {b_name} delimiters must not be prefixes of each other"
+                        ),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
     }
 }
 
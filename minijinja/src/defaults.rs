@@ -48,7 +48,7 @@ pub fn default_auto_escape_callback(name: &str) -> AutoEscape {
 /// * [`None`](AutoEscape::None): no escaping
 /// * [`Custom(..)`](AutoEscape::Custom): results in an error
 pub fn escape_formatter(out: &mut Output, state: &State, value: &Value) -> Result<(), Error> {
-    write_escaped(out, state.auto_escape(), value)
+    write_escaped(out, state, state.auto_escape(), value)
 }
 
 pub(crate) fn get_builtin_filters() -> BTreeMap<Cow<'static, str>, filters::BoxedFilter> {
@@ -63,14 +63,26 @@ pub(crate) fn get_builtin_filters() -> BTreeMap<Cow<'static, str>, filters::Boxe
         rv.insert("upper".into(), BoxedFilter::new(filters::upper));
         rv.insert("title".into(), BoxedFilter::new(filters::title));
         rv.insert("capitalize".into(), BoxedFilter::new(filters::capitalize));
+        rv.insert("wordcount".into(), BoxedFilter::new(filters::wordcount));
+        rv.insert("center".into(), BoxedFilter::new(filters::center));
         rv.insert("replace".into(), BoxedFilter::new(filters::replace));
-        let length = BoxedFilter::new(filters::length);
-        rv.insert("length".into(), length.clone());
-        rv.insert("count".into(), length);
+        rv.insert("length".into(), BoxedFilter::new(filters::length));
+        rv.insert("count".into(), BoxedFilter::new(filters::count));
         rv.insert("dictsort".into(), BoxedFilter::new(filters::dictsort));
         rv.insert("items".into(), BoxedFilter::new(filters::items));
         rv.insert("reverse".into(), BoxedFilter::new(filters::reverse));
         rv.insert("trim".into(), BoxedFilter::new(filters::trim));
+        rv.insert("wrap".into(), BoxedFilter::new(filters::wrap));
+        rv.insert(
+            "truncate_html".into(),
+            BoxedFilter::new(filters::truncate_html),
+        );
+        rv.insert(
+            "truncatewords".into(),
+            BoxedFilter::new(filters::truncatewords),
+        );
+        rv.insert("slugify".into(), BoxedFilter::new(filters::slugify));
+        rv.insert("nl2br".into(), BoxedFilter::new(filters::nl2br));
         rv.insert("join".into(), BoxedFilter::new(filters::join));
         rv.insert("default".into(), BoxedFilter::new(filters::default));
         rv.insert("round".into(), BoxedFilter::new(filters::round));
@@ -78,10 +90,15 @@ pub(crate) fn get_builtin_filters() -> BTreeMap<Cow<'static, str>, filters::Boxe
         rv.insert("int".into(), BoxedFilter::new(filters::int));
         rv.insert("float".into(), BoxedFilter::new(filters::float));
         rv.insert("attr".into(), BoxedFilter::new(filters::attr));
+        rv.insert(
+            "enum_variant".into(),
+            BoxedFilter::new(filters::enum_variant),
+        );
         rv.insert("first".into(), BoxedFilter::new(filters::first));
         rv.insert("last".into(), BoxedFilter::new(filters::last));
         rv.insert("min".into(), BoxedFilter::new(filters::min));
         rv.insert("max".into(), BoxedFilter::new(filters::max));
+        rv.insert("sum".into(), BoxedFilter::new(filters::sum));
         rv.insert("sort".into(), BoxedFilter::new(filters::sort));
         rv.insert("d".into(), BoxedFilter::new(filters::default));
         rv.insert("list".into(), BoxedFilter::new(filters::list));
@@ -89,6 +106,7 @@ pub(crate) fn get_builtin_filters() -> BTreeMap<Cow<'static, str>, filters::Boxe
         rv.insert("batch".into(), BoxedFilter::new(filters::batch));
         rv.insert("slice".into(), BoxedFilter::new(filters::slice));
         rv.insert("indent".into(), BoxedFilter::new(filters::indent));
+        rv.insert("dedent".into(), BoxedFilter::new(filters::dedent));
         rv.insert("select".into(), BoxedFilter::new(filters::select));
         rv.insert("reject".into(), BoxedFilter::new(filters::reject));
         rv.insert("selectattr".into(), BoxedFilter::new(filters::selectattr));
@@ -96,15 +114,21 @@ pub(crate) fn get_builtin_filters() -> BTreeMap<Cow<'static, str>, filters::Boxe
         rv.insert("map".into(), BoxedFilter::new(filters::map));
         rv.insert("unique".into(), BoxedFilter::new(filters::unique));
         rv.insert("pprint".into(), BoxedFilter::new(filters::pprint));
+        rv.insert("xmlattr".into(), BoxedFilter::new(filters::xmlattr));
 
         #[cfg(feature = "json")]
         {
             rv.insert("tojson".into(), BoxedFilter::new(filters::tojson));
+            rv.insert("fromjson".into(), BoxedFilter::new(filters::fromjson));
         }
         #[cfg(feature = "urlencode")]
         {
             rv.insert("urlencode".into(), BoxedFilter::new(filters::urlencode));
         }
+        #[cfg(feature = "markdown")]
+        {
+            rv.insert("markdown".into(), BoxedFilter::new(filters::markdown));
+        }
     }
 
     rv
@@ -180,10 +204,22 @@ pub(crate) fn get_globals() -> BTreeMap<Cow<'static, str>, Value> {
             "dict".into(),
             BoxedFunction::new(functions::dict).to_value(),
         );
+        rv.insert(
+            "coalesce".into(),
+            BoxedFunction::new(functions::coalesce).to_value(),
+        );
+        rv.insert(
+            "cycler".into(),
+            BoxedFunction::new(functions::cycler).to_value(),
+        );
         rv.insert(
             "debug".into(),
             BoxedFunction::new(functions::debug).to_value(),
         );
+        rv.insert(
+            "namespace".into(),
+            BoxedFunction::new(functions::namespace).to_value(),
+        );
     }
 
     rv
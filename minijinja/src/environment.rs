@@ -1,6 +1,6 @@
 use std::borrow::Cow;
 use std::collections::BTreeMap;
-use std::fmt;
+use std::fmt::{self, Write};
 use std::sync::Arc;
 
 use serde::Serialize;
@@ -13,12 +13,18 @@ use crate::error::{attach_basic_debug_info, Error, ErrorKind};
 use crate::expression::Expression;
 use crate::output::Output;
 use crate::template::{CompiledTemplate, CompiledTemplateRef, Template, TemplateConfig};
-use crate::utils::{AutoEscape, BTreeMapKeysDebug, UndefinedBehavior};
-use crate::value::{FunctionArgs, FunctionResult, Value};
+#[cfg(feature = "json")]
+use crate::utils::JsonFloatPolicy;
+use crate::utils::{find_similar, AutoEscape, BTreeMapKeysDebug, MapKeyOrder, UndefinedBehavior};
+use crate::value::{FunctionArgs, FunctionResult, Value, ValueKind};
 use crate::vm::State;
 use crate::{defaults, filters, functions, tests};
 
 type FormatterFunc = dyn Fn(&mut Output, &State, &Value) -> Result<(), Error> + Sync + Send;
+type KindFormatterFunc = dyn Fn(&Value) -> String + Sync + Send;
+type UndefinedAccessFunc = dyn Fn(&State, &str, Option<usize>) + Sync + Send;
+type UnknownFilterFunc =
+    dyn Fn(&str, &State, &[Value]) -> Result<Option<Value>, Error> + Sync + Send;
 type PathJoinFunc = dyn for<'s> Fn(&'s str, &'s str) -> Cow<'s, str> + Sync + Send;
 
 /// The maximum recursion in the VM.  Normally each stack frame
@@ -51,12 +57,23 @@ pub struct Environment<'source> {
     globals: BTreeMap<Cow<'source, str>, Value>,
     path_join_callback: Option<Arc<PathJoinFunc>>,
     undefined_behavior: UndefinedBehavior,
+    map_key_order: MapKeyOrder,
+    collapse_whitespace: bool,
+    loop_length_buffering: bool,
+    #[cfg(feature = "json")]
+    json_float_policy: JsonFloatPolicy,
+    undefined_render: Option<Arc<str>>,
+    undefined_access_callback: Option<Arc<UndefinedAccessFunc>>,
+    unknown_filter_callback: Option<Arc<UnknownFilterFunc>>,
     formatter: Arc<FormatterFunc>,
+    kind_formatters: BTreeMap<ValueKind, Arc<KindFormatterFunc>>,
     #[cfg(feature = "debug")]
     debug: bool,
     #[cfg(feature = "fuel")]
     fuel: Option<u64>,
     recursion_limit: usize,
+    #[cfg(feature = "multi_template")]
+    max_include_depth: Option<usize>,
 }
 
 impl<'source> Default for Environment<'source> {
@@ -93,12 +110,23 @@ impl<'source> Environment<'source> {
             globals: defaults::get_globals(),
             path_join_callback: None,
             undefined_behavior: UndefinedBehavior::default(),
+            map_key_order: MapKeyOrder::default(),
+            collapse_whitespace: false,
+            loop_length_buffering: false,
+            #[cfg(feature = "json")]
+            json_float_policy: JsonFloatPolicy::default(),
+            undefined_render: None,
+            undefined_access_callback: None,
+            unknown_filter_callback: None,
             formatter: Arc::new(defaults::escape_formatter),
+            kind_formatters: BTreeMap::new(),
             #[cfg(feature = "debug")]
             debug: cfg!(debug_assertions),
             #[cfg(feature = "fuel")]
             fuel: None,
             recursion_limit: MAX_RECURSION,
+            #[cfg(feature = "multi_template")]
+            max_include_depth: None,
         }
     }
 
@@ -114,12 +142,23 @@ impl<'source> Environment<'source> {
             globals: Default::default(),
             path_join_callback: None,
             undefined_behavior: UndefinedBehavior::default(),
+            map_key_order: MapKeyOrder::default(),
+            collapse_whitespace: false,
+            loop_length_buffering: false,
+            #[cfg(feature = "json")]
+            json_float_policy: JsonFloatPolicy::default(),
+            undefined_render: None,
+            undefined_access_callback: None,
+            unknown_filter_callback: None,
             formatter: Arc::new(defaults::escape_formatter),
+            kind_formatters: BTreeMap::new(),
             #[cfg(feature = "debug")]
             debug: cfg!(debug_assertions),
             #[cfg(feature = "fuel")]
             fuel: None,
             recursion_limit: MAX_RECURSION,
+            #[cfg(feature = "multi_template")]
+            max_include_depth: None,
         }
     }
 
@@ -206,6 +245,34 @@ impl<'source> Environment<'source> {
         self.templates.set_loader(f);
     }
 
+    /// Adds another template loader after the already registered ones.
+    ///
+    /// Loaders are consulted in the order in which they were added until one
+    /// of them returns a template.  Only if all loaders return `Ok(None)` the
+    /// template is considered missing.  An error returned by a loader aborts
+    /// the lookup.  Calling [`set_loader`](Self::set_loader) replaces all
+    /// loaders registered so far.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use minijinja::{path_loader, Environment};
+    /// fn create_env() -> Environment<'static> {
+    ///     let mut env = Environment::new();
+    ///     env.set_loader(path_loader("path/to/overrides"));
+    ///     env.add_loader(path_loader("path/to/templates"));
+    ///     env
+    /// }
+    /// ```
+    #[cfg(feature = "loader")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "loader")))]
+    pub fn add_loader<F>(&mut self, f: F)
+    where
+        F: Fn(&str) -> Result<Option<String>, Error> + Send + Sync + 'static,
+    {
+        self.templates.add_loader(f);
+    }
+
     /// Preserve the trailing newline when rendering templates.
     ///
     /// The default is `false`, which causes a single newline, if present, to be
@@ -219,6 +286,80 @@ impl<'source> Environment<'source> {
         self.templates.template_config.keep_trailing_newline
     }
 
+    /// Emit template comments into the output.
+    ///
+    /// The default is `false`, which causes `{# ... #}` comments to be stripped.
+    /// When enabled, comments are rendered verbatim including their delimiters
+    /// which can be useful to annotate generated code.  Whitespace control on the
+    /// comment tags still applies.  This only affects templates loaded after the
+    /// flag was changed.
+    pub fn set_keep_comments(&mut self, yes: bool) {
+        self.templates.template_config.lexer_config.keep_comments = yes;
+    }
+
+    /// Returns the value of the comment preservation flag.
+    pub fn keep_comments(&self) -> bool {
+        self.templates.template_config.lexer_config.keep_comments
+    }
+
+    /// Sets the prefix for line statements.
+    ///
+    /// If set, every line that starts with this prefix (after optional
+    /// leading spaces or tabs) is treated as a block statement that ends at
+    /// the end of the line.  A trailing colon is ignored and the statement
+    /// can span multiple lines if parentheses, brackets or braces are still
+    /// open.  Passing `None` (the default) or an empty string turns line
+    /// statements off.  This only affects templates loaded after the prefix
+    /// was changed.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, context};
+    /// let mut env = Environment::new();
+    /// env.set_line_statement_prefix(Some("#".into()));
+    /// let rv = env.render_str("# for item in seq\n[{{ item }}]\n# endfor", context! {
+    ///     seq => vec![1, 2],
+    /// });
+    /// assert_eq!(rv.unwrap(), "[1]\n[2]\n");
+    /// ```
+    pub fn set_line_statement_prefix(&mut self, prefix: Option<String>) {
+        self.templates
+            .template_config
+            .lexer_config
+            .line_statement_prefix = prefix.filter(|x| !x.is_empty()).map(Into::into);
+    }
+
+    /// Returns the prefix for line statements.
+    pub fn line_statement_prefix(&self) -> Option<&str> {
+        self.templates
+            .template_config
+            .lexer_config
+            .line_statement_prefix
+            .as_deref()
+    }
+
+    /// Sets the prefix for line comments.
+    ///
+    /// If set, everything from this prefix to the end of the line is treated
+    /// as a comment.  Spaces and tabs before the prefix are removed as well
+    /// but the newline is retained.  Passing `None` (the default) or an empty
+    /// string turns line comments off.  This only affects templates loaded
+    /// after the prefix was changed.
+    pub fn set_line_comment_prefix(&mut self, prefix: Option<String>) {
+        self.templates
+            .template_config
+            .lexer_config
+            .line_comment_prefix = prefix.filter(|x| !x.is_empty()).map(Into::into);
+    }
+
+    /// Returns the prefix for line comments.
+    pub fn line_comment_prefix(&self) -> Option<&str> {
+        self.templates
+            .template_config
+            .lexer_config
+            .line_comment_prefix
+            .as_deref()
+    }
+
     /// Removes a template by name.
     pub fn remove_template(&mut self, name: &str) {
         self.templates.remove(name);
@@ -263,6 +404,24 @@ impl<'source> Environment<'source> {
         self.templates.clear();
     }
 
+    /// Clears the internal caches of the environment.
+    ///
+    /// This drops all templates that were loaded and compiled through a
+    /// [loader](Self::set_loader) so that they are loaded again from the loader
+    /// on next use.  Unlike [`clear_templates`](Self::clear_templates) templates
+    /// added with [`add_template`](Self::add_template) or
+    /// [`add_template_owned`](Self::add_template_owned) are retained.
+    ///
+    /// This is useful for long running processes that want to pick up changes
+    /// to template sources.  Compiled expressions are not cached by the
+    /// environment and without the `loader` feature this does nothing.
+    pub fn clear_caches(&mut self) {
+        #[cfg(feature = "loader")]
+        {
+            self.templates.clear_caches();
+        }
+    }
+
     /// Fetches a template by name.
     ///
     /// This requires that the template has been loaded with
@@ -362,6 +521,70 @@ impl<'source> Environment<'source> {
         ok!(self.template_from_str(source)).render(ctx)
     }
 
+    /// Parses and renders a template from a string with explicit auto escaping.
+    ///
+    /// Like [`render_str`](Self::render_str), but instead of deriving the
+    /// auto escaping from the template name via the
+    /// [auto escape callback](Self::set_auto_escape_callback) the given
+    /// [`AutoEscape`] mode is used.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, AutoEscape, context};
+    /// let env = Environment::new();
+    /// let rv = env.render_str_autoescaped(
+    ///     "<p>{{ text }}</p>",
+    ///     context!{ text => "Fish & Chips" },
+    ///     AutoEscape::Html,
+    /// );
+    /// assert_eq!(rv.unwrap(), "<p>Fish &amp; Chips</p>");
+    /// ```
+    pub fn render_str_autoescaped<S: Serialize>(
+        &self,
+        source: &str,
+        ctx: S,
+        auto_escape: AutoEscape,
+    ) -> Result<String, Error> {
+        let mut compiled = ok!(CompiledTemplate::new(
+            "<string>",
+            source,
+            &self.templates.template_config,
+        ));
+        compiled.initial_auto_escape = auto_escape;
+        Template::new(self, CompiledTemplateRef::Owned(Arc::new(compiled))).render(ctx)
+    }
+
+    /// Renders multiple templates with the same context.
+    ///
+    /// The context is converted into a [`Value`] once and then shared by all
+    /// templates.  The return value contains the name of each template together
+    /// with the result of rendering it, in the order the names were given.
+    /// Failing to load or render one template does not stop the others from
+    /// being rendered.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, context};
+    /// let mut env = Environment::new();
+    /// env.add_template("a.txt", "A: {{ site }}").unwrap();
+    /// env.add_template("b.txt", "B: {{ site }}").unwrap();
+    /// for (name, rv) in env.render_all(&["a.txt", "b.txt"], context! { site => "Demo" }) {
+    ///     println!("{}: {}", name, rv.unwrap());
+    /// }
+    /// ```
+    pub fn render_all<S: Serialize>(
+        &self,
+        names: &[&str],
+        ctx: S,
+    ) -> Vec<(String, Result<String, Error>)> {
+        let ctx = Value::from_serializable(&ctx);
+        names
+            .iter()
+            .map(|name| {
+                let rv = self.get_template(name).and_then(|tmpl| tmpl.render(&ctx));
+                (name.to_string(), rv)
+            })
+            .collect()
+    }
+
     /// Sets a new function to select the default auto escaping.
     ///
     /// This function is invoked when templates are loaded from the environment
@@ -389,6 +612,51 @@ impl<'source> Environment<'source> {
         self.templates.template_config.default_auto_escape = Arc::new(f);
     }
 
+    /// Uses the same auto escaping mode for all templates.
+    ///
+    /// This is a shortcut for an [auto escape callback](Self::set_auto_escape_callback)
+    /// that ignores the template name.  It replaces the currently configured
+    /// callback, including the default extension based one.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, AutoEscape};
+    /// # let mut env = Environment::new();
+    /// env.set_default_auto_escape(AutoEscape::Html);
+    /// ```
+    pub fn set_default_auto_escape(&mut self, auto_escape: AutoEscape) {
+        self.set_auto_escape_callback(move |_| auto_escape);
+    }
+
+    /// Maps additional file extensions to auto escape modes.
+    ///
+    /// This is a convenience over [`set_auto_escape_callback`](Self::set_auto_escape_callback)
+    /// for the common case where the auto escaping should be picked by file
+    /// extension.  The extensions are given without the leading dot and
+    /// take precedence over the currently configured callback which is
+    /// consulted for all other names.  Like with the default callback a
+    /// final `.j2` extension is ignored.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, AutoEscape};
+    /// # let mut env = Environment::new();
+    /// env.set_auto_escape_extensions(&[("svg", AutoEscape::Html), ("vue", AutoEscape::Html)]);
+    /// ```
+    pub fn set_auto_escape_extensions(&mut self, extensions: &[(&str, AutoEscape)]) {
+        let extensions = extensions
+            .iter()
+            .map(|(ext, auto_escape)| (ext.trim_start_matches('.').to_string(), *auto_escape))
+            .collect::<Vec<_>>();
+        let fallback = self.templates.template_config.default_auto_escape.clone();
+        self.set_auto_escape_callback(move |name| {
+            let ext = name.strip_suffix(".j2").unwrap_or(name).rsplit('.').next();
+            extensions
+                .iter()
+                .find(|(x, _)| Some(x.as_str()) == ext)
+                .map(|(_, auto_escape)| *auto_escape)
+                .unwrap_or_else(|| fallback(name))
+        });
+    }
+
     /// Changes the undefined behavior.
     ///
     /// This changes the runtime behavior of [`undefined`](Value::UNDEFINED) values in
@@ -398,6 +666,174 @@ impl<'source> Environment<'source> {
         self.undefined_behavior = behavior;
     }
 
+    /// Changes the order in which the keys of maps are iterated over.
+    ///
+    /// By default maps are iterated in their own order which is the insertion
+    /// order if the `preserve_order` feature is enabled and sorted by key
+    /// otherwise.  Setting this to [`MapKeyOrder::Sorted`] always iterates
+    /// sorted by key, independent of the feature flags in use:
+    ///
+    /// ```
+    /// # use minijinja::{Environment, MapKeyOrder, context};
+    /// let mut env = Environment::new();
+    /// env.set_map_key_order(MapKeyOrder::Sorted);
+    /// let rv = env.render_str(
+    ///     "{% for key in map %}{{ key }}{% endfor %}",
+    ///     context! { map => context! { b => 1, a => 2 } },
+    /// ).unwrap();
+    /// assert_eq!(rv, "ab");
+    /// ```
+    ///
+    /// This affects `{% for %}` loops and the `items` filter.
+    pub fn set_map_key_order(&mut self, order: MapKeyOrder) {
+        self.map_key_order = order;
+    }
+
+    /// Returns the current map key order.
+    pub fn map_key_order(&self) -> MapKeyOrder {
+        self.map_key_order
+    }
+
+    /// Enables or disables collapsing of whitespace in the rendered output.
+    ///
+    /// When enabled, runs of horizontal whitespace (spaces and tabs) in the
+    /// final output of a template are collapsed into a single space.  Newlines
+    /// are retained.  This can be used as a cheap way to minify generated HTML.
+    /// It applies to [`Template::render`](crate::Template::render) and
+    /// [`Template::render_to_write`](crate::Template::render_to_write).
+    ///
+    /// Note that the engine does not understand the generated output, so this
+    /// also collapses whitespace in places where it matters such as inside of
+    /// `<pre>` or `<textarea>` tags.  The default is `false`.
+    ///
+    /// ```
+    /// # use minijinja::Environment;
+    /// let mut env = Environment::new();
+    /// env.set_collapse_whitespace(true);
+    /// let rv = env.render_str("<p>  {{ 'a' }}\t\t b  </p>", ()).unwrap();
+    /// assert_eq!(rv, "<p> a b </p>");
+    /// ```
+    pub fn set_collapse_whitespace(&mut self, enabled: bool) {
+        self.collapse_whitespace = enabled;
+    }
+
+    /// Returns `true` if whitespace collapsing is enabled.
+    pub fn collapse_whitespace(&self) -> bool {
+        self.collapse_whitespace
+    }
+
+    /// Enables or disables buffering of iterators with an unknown length in loops.
+    ///
+    /// Some values (for instance ones created with
+    /// [`Value::make_iterable`](crate::value::Value::make_iterable)) are
+    /// streamed when iterated over and do not know their length up front.
+    /// In loops over such values `loop.length`, `loop.revindex`,
+    /// `loop.revindex0` and `loop.last` are undefined.  When this is enabled
+    /// the items of such iterators are collected before the loop starts so
+    /// that these attributes work, at the cost of holding all items in memory.
+    /// The default is `false`.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, context, value::Value};
+    /// let mut env = Environment::new();
+    /// env.set_loop_length_buffering(true);
+    /// let seq = Value::make_iterable(|| (1..4).map(Value::from));
+    /// let rv = env.render_str(
+    ///     "{% for x in seq %}{{ x }}{% if not loop.last %},{% endif %}{% endfor %}",
+    ///     context! { seq },
+    /// ).unwrap();
+    /// assert_eq!(rv, "1,2,3");
+    /// ```
+    pub fn set_loop_length_buffering(&mut self, enabled: bool) {
+        self.loop_length_buffering = enabled;
+    }
+
+    /// Returns `true` if iterators with an unknown length are buffered in loops.
+    pub fn loop_length_buffering(&self) -> bool {
+        self.loop_length_buffering
+    }
+
+    /// Sets how non-finite floats are serialized to JSON.
+    ///
+    /// JSON cannot represent `NaN` or infinity.  By default the `tojson`
+    /// filter and [`AutoEscape::Json`] fail with an error when such a value
+    /// is encountered.  With [`JsonFloatPolicy::Null`] they are serialized as
+    /// `null` instead.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, JsonFloatPolicy};
+    /// let mut env = Environment::new();
+    /// env.set_json_float_policy(JsonFloatPolicy::Null);
+    /// let rv = env.render_str("{{ [1.0, nan]|tojson }}", minijinja::context! {
+    ///     nan => f64::NAN,
+    /// }).unwrap();
+    /// assert_eq!(rv, "[1.0,null]");
+    /// ```
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn set_json_float_policy(&mut self, policy: JsonFloatPolicy) {
+        self.json_float_policy = policy;
+    }
+
+    /// Returns the current policy for non-finite floats in JSON.
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn json_float_policy(&self) -> JsonFloatPolicy {
+        self.json_float_policy
+    }
+
+    /// Sets a marker that is printed in place of undefined values.
+    ///
+    /// By default undefined values render as an empty string.  During development
+    /// it can be useful to make missing variables visible instead:
+    ///
+    /// ```
+    /// # use minijinja::Environment;
+    /// let mut env = Environment::new();
+    /// env.set_undefined_render("[UNDEFINED]".to_string());
+    /// let rv = env.render_str("Hello {{ missing }}!", ()).unwrap();
+    /// assert_eq!(rv, "Hello [UNDEFINED]!");
+    /// ```
+    ///
+    /// This only affects printing of undefined values.  The marker is emitted
+    /// as-is and bypasses the [formatter](Self::set_formatter).  Comparisons,
+    /// tests such as `is undefined` and [`UndefinedBehavior::Strict`] (which
+    /// fails on printing undefined values) are not affected.
+    pub fn set_undefined_render(&mut self, marker: String) {
+        self.undefined_render = Some(marker.into());
+    }
+
+    /// Sets a callback that is invoked whenever an undefined value is accessed.
+    ///
+    /// The callback is invoked with the current [`State`], the name of the
+    /// variable or attribute that was looked up and the line in the template
+    /// where the access happened.  It's invoked when a variable cannot be found
+    /// or an attribute is missing on a value that is not itself undefined.
+    /// This is useful for logging mismatches between templates and the data
+    /// passed to them without failing the render:
+    ///
+    /// ```
+    /// # use minijinja::Environment;
+    /// let mut env = Environment::new();
+    /// env.set_undefined_access_callback(|state, name, line| {
+    ///     eprintln!("undefined {:?} in {} on line {:?}", name, state.name(), line);
+    /// });
+    /// ```
+    ///
+    /// The callback is purely informational and does not change how undefined
+    /// values are handled.  Note that it's also invoked for lookups performed
+    /// by checks like `is defined`.
+    pub fn set_undefined_access_callback<F>(&mut self, f: F)
+    where
+        F: Fn(&State, &str, Option<usize>) + 'static + Sync + Send,
+    {
+        self.undefined_access_callback = Some(Arc::new(f));
+    }
+
+    pub(crate) fn undefined_access_callback(&self) -> Option<&UndefinedAccessFunc> {
+        self.undefined_access_callback.as_deref()
+    }
+
     /// Returns the current undefined behavior.
     ///
     /// This is particularly useful if a filter function or similar wants to change its
@@ -407,6 +843,33 @@ impl<'source> Environment<'source> {
         self.undefined_behavior
     }
 
+    /// Sets a callback that is invoked when an unknown filter is used.
+    ///
+    /// The callback is invoked with the name of the filter, the current
+    /// [`State`] and the arguments of the filter (the first argument being
+    /// the value the filter is applied to).  It can return `Ok(Some(value))`
+    /// to provide a result dynamically.  If it returns `Ok(None)` the usual
+    /// [`UnknownFilter`](crate::ErrorKind::UnknownFilter) error is raised.
+    ///
+    /// ```
+    /// # use minijinja::Environment;
+    /// # use minijinja::value::Value;
+    /// let mut env = Environment::new();
+    /// env.set_unknown_filter_callback(|name, _state, args| {
+    ///     Ok(name.strip_prefix("prefix_").map(|prefix| {
+    ///         Value::from(format!("{}{}", prefix, args[0]))
+    ///     }))
+    /// });
+    /// let rv = env.render_str("{{ 'world'|prefix_hello_ }}", ()).unwrap();
+    /// assert_eq!(rv, "hello_world");
+    /// ```
+    pub fn set_unknown_filter_callback<F>(&mut self, f: F)
+    where
+        F: Fn(&str, &State, &[Value]) -> Result<Option<Value>, Error> + 'static + Sync + Send,
+    {
+        self.unknown_filter_callback = Some(Arc::new(f));
+    }
+
     /// Sets a different formatter function.
     ///
     /// The formatter is invoked to format the given value into the provided
@@ -448,6 +911,37 @@ impl<'source> Environment<'source> {
         self.formatter = Arc::new(f);
     }
 
+    /// Sets a function that formats values of a specific kind.
+    ///
+    /// This is a more targeted alternative to [`set_formatter`](Self::set_formatter):
+    /// whenever a value of the given [`ValueKind`] is printed, the function is
+    /// invoked to convert it into a string.  The resulting string is written
+    /// into the output as-is, so the function is responsible for escaping
+    /// according to the current auto escape mode.  Values of other kinds
+    /// render as usual.  Setting a function for the same kind again replaces
+    /// the previous one.
+    ///
+    /// ```
+    /// # use minijinja::Environment;
+    /// use minijinja::value::ValueKind;
+    ///
+    /// let mut env = Environment::new();
+    /// env.set_format_for_kind(ValueKind::Number, |value| {
+    ///     match i64::try_from(value.clone()) {
+    ///         Ok(int) => int.to_string(),
+    ///         Err(_) => format!("{:.2}", f64::try_from(value.clone()).unwrap()),
+    ///     }
+    /// });
+    /// let rv = env.render_str("{{ 1.5 }} {{ 42 }}", ()).unwrap();
+    /// assert_eq!(rv, "1.50 42");
+    /// ```
+    pub fn set_format_for_kind<F>(&mut self, kind: ValueKind, f: F)
+    where
+        F: Fn(&Value) -> String + 'static + Sync + Send,
+    {
+        self.kind_formatters.insert(kind, Arc::new(f));
+    }
+
     /// Enable or disable the debug mode.
     ///
     /// When the debug mode is enabled the engine will dump out some of the
@@ -502,6 +996,10 @@ impl<'source> Environment<'source> {
     /// Note that when `source` is used, the syntax is held on the underlying source
     /// which means that the actual source needs to have it's syntax changed.
     ///
+    /// If a delimiter is empty or a start delimiter is a prefix of another
+    /// one, an error of kind [`InvalidDelimiter`](crate::ErrorKind::InvalidDelimiter)
+    /// is returned and the syntax is left unchanged.
+    ///
     /// See [`Syntax`](crate::Syntax) for more information.
     #[cfg(feature = "custom_syntax")]
     #[cfg_attr(docsrs, doc(cfg(feature = "custom_syntax")))]
@@ -554,6 +1052,27 @@ impl<'source> Environment<'source> {
         self.recursion_limit
     }
 
+    /// Sets the maximum depth of nested includes and extends.
+    ///
+    /// By default template includes and inheritance are only restricted by the
+    /// general [recursion limit](Self::set_recursion_limit), which is shared
+    /// with macro calls.  This sets a dedicated limit on how deeply
+    /// `{% include %}` and `{% extends %}` can be nested, independently of
+    /// how deep macros recurse.  Exceeding it fails rendering with an error
+    /// naming the template that could not be loaded.
+    #[cfg(feature = "multi_template")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "multi_template")))]
+    pub fn set_max_include_depth(&mut self, depth: usize) {
+        self.max_include_depth = Some(depth);
+    }
+
+    /// Returns the maximum include depth if one was configured.
+    #[cfg(feature = "multi_template")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "multi_template")))]
+    pub fn max_include_depth(&self) -> Option<usize> {
+        self.max_include_depth
+    }
+
     /// Compiles an expression.
     ///
     /// This lets one compile an expression in the template language and
@@ -565,6 +1084,25 @@ impl<'source> Environment<'source> {
             .map(|instr| Expression::new(self, instr))
     }
 
+    /// Compiles and evaluates an expression in one go.
+    ///
+    /// This is a shortcut for [`compile_expression`](Self::compile_expression)
+    /// followed by [`Expression::eval`] which does not require the expression
+    /// source to outlive the environment.  If the same expression is evaluated
+    /// many times it's more efficient to compile it once and hold on to the
+    /// [`Expression`].
+    ///
+    /// ```
+    /// # use minijinja::{Environment, context};
+    /// let env = Environment::new();
+    /// let rv = env.eval_expr("a + b * 2", context! { a => 1, b => 2 }).unwrap();
+    /// assert_eq!(rv, 5.into());
+    /// ```
+    pub fn eval_expr<S: Serialize>(&self, expr: &str, ctx: S) -> Result<Value, Error> {
+        let instr = ok!(self._compile_expression(expr));
+        crate::expression::eval_instructions(self, &instr, Value::from_serializable(&ctx))
+    }
+
     /// Compiles an expression without capturing the lifetime.
     ///
     /// This works exactly like [`compile_expression`](Self::compile_expression) but
@@ -606,15 +1144,102 @@ impl<'source> Environment<'source> {
         Rv: FunctionResult,
         Args: for<'a> FunctionArgs<'a>,
     {
-        self.filters
-            .insert(name.into(), filters::BoxedFilter::new(f));
+        let name = name.into();
+        self.set_streaming_filter_flag(&name, false);
+        self.filters.insert(name, filters::BoxedFilter::new(f));
     }
 
     /// Removes a filter by name.
     pub fn remove_filter(&mut self, name: &str) {
+        self.set_streaming_filter_flag(name, false);
         self.filters.remove(name);
     }
 
+    /// Returns `true` if a filter with the given name is registered.
+    ///
+    /// ```
+    /// # use minijinja::Environment;
+    /// let env = Environment::new();
+    /// assert!(env.has_filter("safe"));
+    /// assert!(!env.has_filter("missing"));
+    /// ```
+    pub fn has_filter(&self, name: &str) -> bool {
+        self.filters.contains_key(name)
+    }
+
+    /// Looks up a filter by name.
+    ///
+    /// The returned handle can be used to invoke the filter, for instance to
+    /// build a filter that delegates to another one:
+    ///
+    /// ```
+    /// # use minijinja::{Environment, State, Value, Error};
+    /// let mut env = Environment::new();
+    /// env.add_filter("loud", |state: &State, value: Value| -> Result<Value, Error> {
+    ///     let safe = state.env().get_filter("safe").unwrap();
+    ///     safe.apply_to(state, &[Value::from(format!("<b>{value}</b>"))])
+    /// });
+    /// ```
+    pub fn get_filter(&self, name: &str) -> Option<&filters::BoxedFilter> {
+        self.filters.get(name)
+    }
+
+    /// Adds a new filter that writes directly into the output.
+    ///
+    /// Regular filters return a value which is then formatted into the output.
+    /// For filters that produce large amounts of output this intermediate
+    /// value can be avoided by writing into the [`Output`] directly.  The
+    /// function is invoked with the current [`State`], the output and the
+    /// arguments of the filter (the first argument being the value the filter
+    /// is applied to).  Use [`from_args`](crate::value::from_args) to convert
+    /// the arguments.
+    ///
+    /// The filter writes into the output as-is so it is responsible for
+    /// escaping.  If the filter is used anywhere but directly in a print
+    /// statement (for instance if another filter is applied on its result)
+    /// the output is captured into a string.  Like the output of a
+    /// `{% set %}` block, that string is marked as safe if auto escaping
+    /// is enabled.  Whether a filter streams is decided when a template is
+    /// compiled, so the filter should be registered before the templates
+    /// using it are added.  Templates added earlier capture its output too.
+    ///
+    /// ```
+    /// # use minijinja::Environment;
+    /// # let mut env = Environment::new();
+    /// use minijinja::value::from_args;
+    ///
+    /// env.add_streaming_filter("repeat", |_state, out, args| {
+    ///     let (value, count): (&str, usize) = from_args(args)?;
+    ///     for _ in 0..count {
+    ///         out.write_str(value)?;
+    ///     }
+    ///     Ok(())
+    /// });
+    /// # assert_eq!(env.render_str("{{ 'ab'|repeat(2) }}", ()).unwrap(), "abab");
+    /// ```
+    pub fn add_streaming_filter<N, F>(&mut self, name: N, f: F)
+    where
+        N: Into<Cow<'source, str>>,
+        F: Fn(&State, &mut Output, &[Value]) -> Result<(), Error> + Send + Sync + 'static,
+    {
+        let name = name.into();
+        self.set_streaming_filter_flag(&name, true);
+        self.filters
+            .insert(name, filters::BoxedFilter::new_streaming(f));
+    }
+
+    fn set_streaming_filter_flag(&mut self, name: &str, streaming: bool) {
+        let names = &mut self.templates.template_config.streaming_filters;
+        if names.contains(name) != streaming {
+            let names = Arc::make_mut(names);
+            if streaming {
+                names.insert(name.to_string());
+            } else {
+                names.remove(name);
+            }
+        }
+    }
+
     /// Adds a new test function.
     ///
     /// Test functions are similar to filters but perform a check on a value
@@ -636,6 +1261,18 @@ impl<'source> Environment<'source> {
         self.tests.remove(name);
     }
 
+    /// Returns `true` if a test with the given name is registered.
+    pub fn has_test(&self, name: &str) -> bool {
+        self.tests.contains_key(name)
+    }
+
+    /// Looks up a test by name.
+    ///
+    /// The returned handle can be used to perform the test.
+    pub fn get_test(&self, name: &str) -> Option<&tests::BoxedTest> {
+        self.tests.get(name)
+    }
+
     /// Adds a new global function.
     ///
     /// For details about functions have a look at [`functions`].  Note that
@@ -663,6 +1300,32 @@ impl<'source> Environment<'source> {
         self.globals.insert(name.into(), value.into());
     }
 
+    /// Adds a global variable from a serializable value.
+    ///
+    /// The value is converted into a [`Value`] once when it's registered so
+    /// that renders do not need to serialize it again.  This is useful to
+    /// expose configuration structs to all templates:
+    ///
+    /// ```
+    /// # use minijinja::Environment;
+    /// #[derive(serde::Serialize)]
+    /// struct Config {
+    ///     site_name: &'static str,
+    /// }
+    ///
+    /// let mut env = Environment::new();
+    /// env.add_global_serialize("config", &Config { site_name: "My Site" });
+    /// let rv = env.render_str("{{ config.site_name }}", ()).unwrap();
+    /// assert_eq!(rv, "My Site");
+    /// ```
+    pub fn add_global_serialize<N, T>(&mut self, name: N, value: &T)
+    where
+        N: Into<Cow<'source, str>>,
+        T: Serialize,
+    {
+        self.add_global(name, Value::from_serializable(value));
+    }
+
     /// Removes a global function or variable by name.
     pub fn remove_global(&mut self, name: &str) {
         self.globals.remove(name);
@@ -678,14 +1341,23 @@ impl<'source> Environment<'source> {
         self.globals.get(name).cloned()
     }
 
-    /// Looks up a filter.
-    pub(crate) fn get_filter(&self, name: &str) -> Option<&filters::BoxedFilter> {
-        self.filters.get(name)
-    }
-
-    /// Looks up a test function.
-    pub(crate) fn get_test(&self, name: &str) -> Option<&tests::BoxedTest> {
-        self.tests.get(name)
+    /// Handles the invocation of a filter that is not registered.
+    pub(crate) fn apply_unknown_filter(
+        &self,
+        name: &str,
+        state: &State,
+        args: &[Value],
+    ) -> Result<Value, Error> {
+        if let Some(ref callback) = self.unknown_filter_callback {
+            if let Some(rv) = ok!(callback(name, state, args)) {
+                return Ok(rv);
+            }
+        }
+        let mut msg = format!("filter {name} is unknown");
+        if let Some(similar) = find_similar(name, self.filters.keys().map(|x| x as &str)) {
+            write!(msg, " (did you mean {similar}?)").ok();
+        }
+        Err(Error::new(ErrorKind::UnknownFilter, msg))
     }
 
     pub(crate) fn initial_auto_escape(&self, name: &str) -> AutoEscape {
@@ -705,6 +1377,10 @@ impl<'source> Environment<'source> {
     ) -> Result<(), Error> {
         if value.is_undefined() && matches!(self.undefined_behavior, UndefinedBehavior::Strict) {
             Err(Error::from(ErrorKind::UndefinedError))
+        } else if let (true, Some(marker)) = (value.is_undefined(), &self.undefined_render) {
+            out.write_str(marker).map_err(Error::from)
+        } else if let Some(f) = self.kind_formatters.get(&value.kind()) {
+            out.write_str(&f(value)).map_err(Error::from)
         } else {
             (self.formatter)(out, state, value)
         }
@@ -253,7 +253,20 @@ impl Error {
     }
 
     /// Attaches another error as source to this error.
-    #[allow(unused)]
+    ///
+    /// The attached error is returned from [`source`](std::error::Error::source)
+    /// so that the full chain of causes can be reported.  This is useful for
+    /// custom filters and functions that fail because of an underlying error:
+    ///
+    /// ```
+    /// use minijinja::{Error, ErrorKind};
+    ///
+    /// fn parse_int(value: &str) -> Result<i64, Error> {
+    ///     value.parse().map_err(|err| {
+    ///         Error::new(ErrorKind::InvalidOperation, "not an integer").with_source(err)
+    ///     })
+    /// }
+    /// ```
     pub fn with_source<E: std::error::Error + Send + Sync + 'static>(mut self, source: E) -> Self {
         self.repr.source = Some(Box::new(source));
         self
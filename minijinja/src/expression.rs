@@ -110,14 +110,23 @@ impl<'env, 'source> Expression<'env, 'source> {
     }
 
     fn _eval(&self, root: Value) -> Result<Value, Error> {
-        Ok(ok!(Vm::new(self.env).eval(
-            self.instructions(),
-            root,
-            &BTreeMap::new(),
-            &mut Output::null(),
-            crate::AutoEscape::None,
-        ))
-        .0
-        .expect("expression evaluation did not leave value on stack"))
+        eval_instructions(self.env, self.instructions(), root)
     }
 }
+
+/// Evaluates compiled expression instructions against a root value.
+pub(crate) fn eval_instructions(
+    env: &Environment,
+    instructions: &Instructions,
+    root: Value,
+) -> Result<Value, Error> {
+    Ok(ok!(Vm::new(env).eval(
+        instructions,
+        root,
+        &BTreeMap::new(),
+        &mut Output::null(),
+        crate::AutoEscape::None,
+    ))
+    .0
+    .expect("expression evaluation did not leave value on stack"))
+}
@@ -83,9 +83,16 @@ use crate::vm::State;
 use crate::{AutoEscape, Output};
 
 type FilterFunc = dyn Fn(&State, &[Value]) -> Result<Value, Error> + Sync + Send + 'static;
+type StreamingFilterFunc =
+    dyn Fn(&State, &mut Output, &[Value]) -> Result<(), Error> + Sync + Send + 'static;
 
+/// A handle to a filter registered with an environment.
+///
+/// Handles are returned by [`Environment::get_filter`](crate::Environment::get_filter)
+/// and can be used to invoke a filter by name, for instance from within
+/// another filter.
 #[derive(Clone)]
-pub(crate) struct BoxedFilter(Arc<FilterFunc>);
+pub struct BoxedFilter(Arc<FilterFunc>, Option<Arc<StreamingFilterFunc>>);
 
 /// A utility trait that represents filters.
 ///
@@ -195,22 +202,68 @@ tuple_impls! { A B C D E }
 
 impl BoxedFilter {
     /// Creates a new boxed filter.
-    pub fn new<F, Rv, Args>(f: F) -> BoxedFilter
+    pub(crate) fn new<F, Rv, Args>(f: F) -> BoxedFilter
     where
         F: Filter<Rv, Args> + for<'a> Filter<Rv, <Args as FunctionArgs<'a>>::Output>,
         Rv: FunctionResult,
         Args: for<'a> FunctionArgs<'a>,
     {
-        BoxedFilter(Arc::new(move |state, args| -> Result<Value, Error> {
-            f.apply_to(ok!(Args::from_values(Some(state), args)), SealedMarker)
-                .into_result()
-        }))
+        BoxedFilter(
+            Arc::new(move |state, args| -> Result<Value, Error> {
+                f.apply_to(ok!(Args::from_values(Some(state), args)), SealedMarker)
+                    .into_result()
+            }),
+            None,
+        )
+    }
+
+    /// Creates a new boxed filter that writes directly into the output.
+    ///
+    /// When used in any other place than directly in a print statement, the
+    /// output is captured into a string instead.
+    pub(crate) fn new_streaming<F>(f: F) -> BoxedFilter
+    where
+        F: Fn(&State, &mut Output, &[Value]) -> Result<(), Error> + Sync + Send + 'static,
+    {
+        let f = Arc::new(f);
+        BoxedFilter(
+            Arc::new({
+                let f = f.clone();
+                move |state, args| -> Result<Value, Error> {
+                    let mut rv = String::new();
+                    ok!(f(state, &mut Output::with_string(&mut rv), args));
+                    Ok(if matches!(state.auto_escape(), AutoEscape::None) {
+                        Value::from(rv)
+                    } else {
+                        Value::from_safe_string(rv)
+                    })
+                }
+            }),
+            Some(f),
+        )
     }
 
-    /// Applies the filter to a value and argument.
+    /// Applies the filter to a value and arguments.
+    ///
+    /// The value the filter is applied to is the first argument.
     pub fn apply_to(&self, state: &State, args: &[Value]) -> Result<Value, Error> {
         (self.0)(state, args)
     }
+
+    /// Applies the filter and writes the result into the output.
+    pub(crate) fn emit_to(
+        &self,
+        state: &State,
+        args: &[Value],
+        out: &mut Output,
+    ) -> Result<(), Error> {
+        match self.1 {
+            Some(ref f) => f(state, out, args),
+            None => state
+                .env
+                .format(&ok!(self.apply_to(state, args)), state, out),
+        }
+    }
 }
 
 /// Marks a value as safe.  This converts it into a string.
@@ -226,6 +279,15 @@ pub fn safe(v: String) -> Value {
 /// this filter escapes with the format that is native to the format or HTML
 /// otherwise.  This means that if the auto escape setting is set to
 /// `Json` for instance then this filter will serialize to JSON instead.
+///
+/// The return value is marked as safe and values that are already safe are
+/// returned unchanged.  This makes the filter idempotent so escaping twice
+/// (or escaping the result of [`safe`]) never double escapes:
+///
+/// ```jinja
+/// {{ "<b>"|escape|escape }}  -> &lt;b&gt;
+/// {{ "<b>"|safe|escape }}    -> <b>
+/// ```
 pub fn escape(state: &State, v: Value) -> Result<Value, Error> {
     if v.is_safe() {
         return Ok(v);
@@ -246,7 +308,7 @@ pub fn escape(state: &State, v: Value) -> Result<Value, Error> {
         None => String::new(),
     };
     let mut out = Output::with_string(&mut rv);
-    ok!(write_escaped(&mut out, auto_escape, &v));
+    ok!(write_escaped(&mut out, state, auto_escape, &v));
     Ok(Value::from_safe_string(rv))
 }
 
@@ -255,7 +317,8 @@ mod builtins {
     use super::*;
 
     use crate::error::ErrorKind;
-    use crate::value::ops::as_f64;
+    use crate::utils::HtmlEscape;
+    use crate::value::ops::{self, as_f64};
     use crate::value::{Kwargs, ValueKind, ValueRepr};
     use std::borrow::Cow;
     use std::cmp::Ordering;
@@ -308,25 +371,76 @@ mod builtins {
     /// Convert the string with all its characters lowercased
     /// apart from the first char which is uppercased.
     ///
+    /// This matches the behavior of Python's `str.capitalize`.  If the
+    /// first character uppercases to multiple characters (eg: `ß` to `SS`)
+    /// only the first of those is kept uppercase.
+    ///
     /// ```jinja
     /// <h1>{{ chapter.title|capitalize }}</h1>
     /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn capitalize(text: Cow<'_, str>) -> String {
         let mut chars = text.chars();
-        match chars.next() {
-            None => String::new(),
-            Some(f) => f.to_uppercase().collect::<String>() + &chars.as_str().to_lowercase(),
+        let mut rv = String::with_capacity(text.len());
+        if let Some(f) = chars.next() {
+            let mut upper = f.to_uppercase();
+            rv.extend(upper.next());
+            rv.extend(upper.flat_map(char::to_lowercase));
+            rv.push_str(&chars.as_str().to_lowercase());
         }
+        rv
+    }
+
+    /// Counts the words in a string.
+    ///
+    /// Words are separated by any amount of Unicode whitespace.  Leading and
+    /// trailing whitespace is ignored so an empty string has zero words.
+    ///
+    /// ```jinja
+    /// <p>{{ article.body|wordcount }} words</p>
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
+    pub fn wordcount(text: Cow<'_, str>) -> usize {
+        text.split_whitespace().count()
+    }
+
+    /// Centers a string in a field of a given width.
+    ///
+    /// The width defaults to `80`.  The string is padded with spaces on both
+    /// sides and follows Python's `str.center` on where an odd amount of
+    /// padding goes.  Strings that are already longer than the width are
+    /// returned unchanged.
+    ///
+    /// ```jinja
+    /// <pre>{{ "Table of Contents"|center(40) }}</pre>
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
+    pub fn center(text: Cow<'_, str>, width: Option<usize>) -> String {
+        let width = width.unwrap_or(80);
+        let len = text.chars().count();
+        if len >= width {
+            return text.into_owned();
+        }
+        let pad = width - len;
+        let left = pad / 2 + (pad & width & 1);
+        let mut rv = String::with_capacity(text.len() + pad);
+        rv.extend(std::iter::repeat(' ').take(left));
+        rv.push_str(&text);
+        rv.extend(std::iter::repeat(' ').take(pad - left));
+        rv
     }
 
     /// Does a string replace.
     ///
     /// It replaces all occurrences of the first parameter with the second.
+    /// If the optional third parameter is given, only the first `count`
+    /// occurrences are replaced.
     ///
     /// ```jinja
     /// {{ "Hello World"|replace("Hello", "Goodbye") }}
     ///   -> Goodbye World
+    /// {{ "aaa"|replace("a", "b", 1) }}
+    ///   -> baa
     /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn replace(
@@ -334,13 +448,18 @@ mod builtins {
         v: Cow<'_, str>,
         from: Cow<'_, str>,
         to: Cow<'_, str>,
+        count: Option<usize>,
     ) -> String {
-        v.replace(&from as &str, &to as &str)
+        match count {
+            Some(count) => v.replacen(&from as &str, &to as &str, count),
+            None => v.replace(&from as &str, &to as &str),
+        }
     }
 
     /// Returns the "length" of the value
     ///
-    /// By default this filter is also registered under the alias `count`.
+    /// By default this filter is also available as `count` which in addition
+    /// can count the occurrences of a substring (see [`count`]).
     ///
     /// ```jinja
     /// <p>Search results: {{ results|length }}
@@ -355,6 +474,37 @@ mod builtins {
         })
     }
 
+    /// Returns the length of the value or counts occurrences of a substring.
+    ///
+    /// Without an argument this is an alias of [`length`].  If a string is
+    /// passed, the number of non-overlapping occurrences of that string in
+    /// the value is returned instead.
+    ///
+    /// ```jinja
+    /// {{ "banana"|count("an") }}
+    ///   -> 2
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
+    pub fn count(v: Value, needle: Option<Cow<'_, str>>) -> Result<usize, Error> {
+        let needle = match needle {
+            Some(needle) => needle,
+            None => return length(v),
+        };
+        let haystack = ok!(v.as_str().ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidOperation,
+                format!("cannot count substrings in value of type {}", v.kind()),
+            )
+        }));
+        if needle.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidOperation,
+                "cannot count occurrences of an empty string",
+            ));
+        }
+        Ok(haystack.matches(&needle as &str).count())
+    }
+
     fn sort_helper(a: &Value, b: &Value, case_sensitive: bool) -> Ordering {
         if !case_sensitive {
             if let (Some(a), Some(b)) = (a.as_str(), b.as_str()) {
@@ -368,7 +518,7 @@ mod builtins {
                 }
             }
         }
-        a.cmp(b)
+        ops::cmp(a, b)
     }
 
     /// Dict sorting functionality.
@@ -427,6 +577,8 @@ mod builtins {
     /// at once.  Note that this will use the original order of the map
     /// which is typically arbitrary unless the `preserve_order` feature
     /// is used in which case the original order of the map is retained.
+    /// The order can also be forced to be sorted by key with
+    /// [`Environment::set_map_key_order`](crate::Environment::set_map_key_order).
     /// It's generally better to use `|dictsort` which sorts the map by
     /// key before iterating.
     ///
@@ -439,10 +591,11 @@ mod builtins {
     /// </dl>
     /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
-    pub fn items(v: Value) -> Result<Value, Error> {
+    pub fn items(state: &State, v: Value) -> Result<Value, Error> {
         if v.kind() == ValueKind::Map {
             let mut rv = Vec::with_capacity(v.len().unwrap_or(0));
-            let iter = ok!(v.try_iter());
+            let keys = state.env().map_key_order().apply(v.clone());
+            let iter = ok!(keys.try_iter());
             for key in iter {
                 let value = v.get_item(&key).unwrap_or(Value::UNDEFINED);
                 rv.push(Value::from(vec![key, value]));
@@ -458,6 +611,11 @@ mod builtins {
 
     /// Reverses a list or string
     ///
+    /// Sequences are returned as a new sequence with the items in reverse
+    /// order.  Strings are reversed by Unicode scalar value (`char`) and
+    /// returned as string.  Grapheme clusters made of multiple scalar values
+    /// (for instance letters followed by combining marks) are not kept together.
+    ///
     /// ```jinja
     /// {% for user in users|reverse %}
     ///   <li>{{ user.name }}
@@ -489,9 +647,252 @@ mod builtins {
         }
     }
 
+    /// Surrounds a value with a prefix and suffix.
+    ///
+    /// The value is converted into a string and wrapped in the given
+    /// `prefix` and `suffix`.  If no suffix is given the prefix is used
+    /// on both sides.
+    ///
+    /// ```jinja
+    /// {{ name|wrap('"') }} -> "name"
+    /// {{ args|join(", ")|wrap("(", ")") }} -> (a, b)
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
+    pub fn wrap(value: &Value, prefix: Cow<'_, str>, suffix: Option<Cow<'_, str>>) -> String {
+        let suffix = suffix.as_deref().unwrap_or(&prefix);
+        format!("{prefix}{value}{suffix}")
+    }
+
+    /// Truncates an HTML string to a number of visible characters.
+    ///
+    /// Only text outside of tags counts towards the length (an entity such as
+    /// `&amp;` counts as one character).  If the text is longer than `length`
+    /// (defaults to `255`) it's cut off, the `end` marker (defaults to `...`)
+    /// is appended and all tags still open at that point are closed again, so
+    /// the result is balanced markup.  The input is assumed to be trusted HTML
+    /// and the return value is marked as safe.
+    ///
+    /// ```jinja
+    /// <div class="excerpt">{{ post.body|truncate_html(200) }}</div>
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
+    pub fn truncate_html(
+        value: Cow<'_, str>,
+        length: Option<usize>,
+        end: Option<Cow<'_, str>>,
+    ) -> Value {
+        const VOID_ELEMENTS: &[&str] = &[
+            "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source",
+            "track", "wbr",
+        ];
+
+        let length = length.unwrap_or(255);
+        let mut rv = String::with_capacity(value.len());
+        let mut open_tags = Vec::new();
+        let mut visible = 0;
+        let mut rest = &value[..];
+
+        while let Some(c) = rest.chars().next() {
+            if c == '<' {
+                let tag_end = match rest.find('>') {
+                    Some(idx) => idx + 1,
+                    None => rest.len(),
+                };
+                let tag = &rest[..tag_end];
+                rest = &rest[tag_end..];
+                rv.push_str(tag);
+                if tag.starts_with("<!") || tag.starts_with("<?") || tag.ends_with("/>") {
+                    continue;
+                }
+                let (closing, inner) = match tag[1..].strip_prefix('/') {
+                    Some(inner) => (true, inner),
+                    None => (false, &tag[1..]),
+                };
+                let name = inner
+                    .split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+                    .next()
+                    .unwrap_or("")
+                    .to_ascii_lowercase();
+                if name.is_empty() || VOID_ELEMENTS.contains(&name.as_str()) {
+                    continue;
+                }
+                if closing {
+                    if let Some(pos) = open_tags.iter().rposition(|x| *x == name) {
+                        open_tags.truncate(pos);
+                    }
+                } else {
+                    open_tags.push(name);
+                }
+                continue;
+            }
+
+            if visible == length {
+                rv.push_str(end.as_deref().unwrap_or("..."));
+                for tag in open_tags.iter().rev() {
+                    write!(rv, "</{}>", tag).unwrap();
+                }
+                return Value::from_safe_string(rv);
+            }
+
+            let char_len = if c == '&' {
+                match rest.find(';') {
+                    Some(idx)
+                        if rest[1..idx]
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '#') =>
+                    {
+                        idx + 1
+                    }
+                    _ => 1,
+                }
+            } else {
+                c.len_utf8()
+            };
+            rv.push_str(&rest[..char_len]);
+            rest = &rest[char_len..];
+            visible += 1;
+        }
+
+        Value::from_safe_string(rv)
+    }
+
+    /// Truncates a string to a number of words.
+    ///
+    /// Words are separated by any amount of Unicode whitespace.  If the string
+    /// has more than `count` words, only the first `count` words are kept
+    /// (joined by a single space) and the `end` marker (defaults to `...`) is
+    /// appended.  Otherwise the string is returned unchanged.
+    ///
+    /// ```jinja
+    /// <p class="teaser">{{ article.body|truncatewords(15) }}</p>
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
+    pub fn truncatewords(value: Cow<'_, str>, count: usize, end: Option<Cow<'_, str>>) -> String {
+        let mut words = value.split_whitespace();
+        let kept = words.by_ref().take(count).collect::<Vec<_>>();
+        if words.next().is_none() {
+            return value.into_owned();
+        }
+        let mut rv = kept.join(" ");
+        rv.push_str(end.as_deref().unwrap_or("..."));
+        rv
+    }
+
+    /// Converts a string into a slug suitable for URLs.
+    ///
+    /// The result is lowercase ASCII.  Common accented Latin characters are
+    /// transliterated (`é` becomes `e`, `ß` becomes `ss`), all other runs of
+    /// non-alphanumeric characters are replaced by a single separator and
+    /// leading and trailing separators are removed.  The separator defaults
+    /// to `-` and can be changed with the `separator` keyword argument.
+    ///
+    /// ```jinja
+    /// <a href="/blog/{{ post.title|slugify }}">{{ post.title }}</a>
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
+    pub fn slugify(value: Cow<'_, str>, kwargs: Kwargs) -> Result<String, Error> {
+        let separator = ok!(kwargs.get::<Option<Cow<'_, str>>>("separator"));
+        let separator = separator.as_deref().unwrap_or("-");
+        ok!(kwargs.assert_all_used());
+
+        let mut rv = String::with_capacity(value.len());
+        let mut pending_separator = false;
+        let mut buf = [0; 4];
+        for c in value.chars() {
+            let piece = if c.is_ascii_alphanumeric() {
+                &*c.to_ascii_lowercase().encode_utf8(&mut buf)
+            } else if let Some(replacement) = transliterate(c) {
+                replacement
+            } else {
+                pending_separator = true;
+                continue;
+            };
+            if pending_separator && !rv.is_empty() {
+                rv.push_str(separator);
+            }
+            pending_separator = false;
+            rv.push_str(piece);
+        }
+        Ok(rv)
+    }
+
+    /// Maps common accented Latin characters to ASCII.
+    fn transliterate(c: char) -> Option<&'static str> {
+        Some(match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+            'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' | 'Ā' | 'Ă' | 'Ą' => "a",
+            'æ' | 'Æ' => "ae",
+            'ç' | 'ć' | 'č' | 'Ç' | 'Ć' | 'Č' => "c",
+            'ď' | 'đ' | 'ð' | 'Ď' | 'Đ' | 'Ð' => "d",
+            'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+            'È' | 'É' | 'Ê' | 'Ë' | 'Ē' | 'Ė' | 'Ę' | 'Ě' => "e",
+            'ğ' | 'Ğ' => "g",
+            'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' | 'ı' => "i",
+            'Ì' | 'Í' | 'Î' | 'Ï' | 'Ī' | 'Į' | 'İ' => "i",
+            'ł' | 'Ł' => "l",
+            'ñ' | 'ń' | 'ň' | 'Ñ' | 'Ń' | 'Ň' => "n",
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+            'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' | 'Ō' | 'Ő' => "o",
+            'œ' | 'Œ' => "oe",
+            'ř' | 'Ř' => "r",
+            'ś' | 'š' | 'ş' | 'Ś' | 'Š' | 'Ş' => "s",
+            'ß' => "ss",
+            'ť' | 'ţ' | 'Ť' | 'Ţ' => "t",
+            'þ' | 'Þ' => "th",
+            'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' | 'ų' => "u",
+            'Ù' | 'Ú' | 'Û' | 'Ü' | 'Ū' | 'Ů' | 'Ű' | 'Ų' => "u",
+            'ý' | 'ÿ' | 'Ý' | 'Ÿ' => "y",
+            'ź' | 'ż' | 'ž' | 'Ź' | 'Ż' | 'Ž' => "z",
+            _ => return None,
+        })
+    }
+
+    /// Converts newlines into HTML line breaks.
+    ///
+    /// The text is HTML escaped (unless it's already marked as safe) and every
+    /// line break (`\n` or `\r\n`) is replaced by `<br>` followed by a newline.
+    /// The return value is marked as safe so the inserted tags are not escaped
+    /// again.
+    ///
+    /// ```jinja
+    /// <p class="comment">{{ comment.text|nl2br }}</p>
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
+    pub fn nl2br(value: Value) -> Value {
+        let is_safe = value.is_safe();
+        let text = value.to_string();
+        let mut rv = String::with_capacity(text.len());
+        let mut lines = text.split('\n').peekable();
+        while let Some(mut line) = lines.next() {
+            let has_break = lines.peek().is_some();
+            if has_break {
+                line = line.strip_suffix('\r').unwrap_or(line);
+            }
+            if is_safe {
+                rv.push_str(line);
+            } else {
+                write!(rv, "{}", HtmlEscape(line)).unwrap();
+            }
+            if has_break {
+                rv.push_str("<br>\n");
+            }
+        }
+        Value::from_safe_string(rv)
+    }
+
     /// Joins a sequence by a character
+    ///
+    /// Optionally the `attribute` keyword argument can be set to an attribute
+    /// or dotted path which is then looked up on each item before joining.
+    ///
+    /// ```jinja
+    /// {{ users|join(", ", attribute="name") }}
+    /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
-    pub fn join(val: Value, joiner: Option<Cow<'_, str>>) -> Result<String, Error> {
+    pub fn join(val: Value, joiner: Option<Cow<'_, str>>, kwargs: Kwargs) -> Result<String, Error> {
+        let attr = ok!(kwargs.get::<Option<&str>>("attribute"));
+        ok!(kwargs.assert_all_used());
+
         if val.is_undefined() || val.is_none() {
             return Ok(String::new());
         }
@@ -513,6 +914,10 @@ mod builtins {
                 if !rv.is_empty() {
                     rv.push_str(joiner);
                 }
+                let item = match attr {
+                    Some(attr) => ok!(item.get_path(attr)),
+                    None => item,
+                };
                 if let Some(s) = item.as_str() {
                     rv.push_str(s);
                 } else {
@@ -572,105 +977,246 @@ mod builtins {
 
     /// Converts a value into an integer.
     ///
+    /// Strings are parsed in base 10 unless a different `base` is given.  For
+    /// bases 2, 8 and 16 the matching `0b`, `0o` and `0x` prefix is allowed,
+    /// and a base of 0 detects the base from the prefix.  Strings that are
+    /// not integers are parsed as floats instead.  Floats are truncated and
+    /// booleans become `0` or `1`.  If the value cannot be converted the
+    /// `default` (which defaults to `0`) is returned.
+    ///
     /// ```jinja
     /// {{ "42"|int == 42 }} -> true
+    /// {{ "0xff"|int(base=16) }} -> 255
+    /// {{ "nope"|int(-1) }} -> -1
+    /// {{ 42.9|int }} -> 42
     /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
-    pub fn int(value: Value) -> Result<Value, Error> {
-        match &value.0 {
-            ValueRepr::Undefined | ValueRepr::None => Ok(Value::from(0)),
-            ValueRepr::Bool(x) => Ok(Value::from(*x as u64)),
+    pub fn int(
+        value: Value,
+        default: Option<Value>,
+        base: Option<u32>,
+        kwargs: Kwargs,
+    ) -> Result<Value, Error> {
+        let default = match default {
+            Some(default) => default,
+            None => ok!(kwargs.get::<Option<Value>>("default")).unwrap_or(Value::from(0)),
+        };
+        let base = match base {
+            Some(base) => base,
+            None => ok!(kwargs.get::<Option<u32>>("base")).unwrap_or(10),
+        };
+        ok!(kwargs.assert_all_used());
+        if base == 1 || base > 36 {
+            return Err(Error::new(
+                ErrorKind::InvalidOperation,
+                "base must be 0 or between 2 and 36",
+            ));
+        }
+
+        fn float_to_int(v: f64) -> Option<Value> {
+            let x = v.trunc();
+            if x.is_finite() && x >= i128::MIN as f64 && x <= i128::MAX as f64 {
+                Some(Value::from(x as i128))
+            } else {
+                None
+            }
+        }
+
+        fn parse_int(s: &str, base: u32) -> Option<Value> {
+            let (neg, digits) = match s.strip_prefix('-') {
+                Some(rest) => (true, rest),
+                None => (false, s.strip_prefix('+').unwrap_or(s)),
+            };
+            let has_prefix = |prefix: &str| {
+                digits
+                    .as_bytes()
+                    .get(..2)
+                    .map_or(false, |x| x.eq_ignore_ascii_case(prefix.as_bytes()))
+            };
+            let (base, digits) = match base {
+                0 | 16 if has_prefix("0x") => (16, &digits[2..]),
+                0 | 8 if has_prefix("0o") => (8, &digits[2..]),
+                0 | 2 if has_prefix("0b") => (2, &digits[2..]),
+                0 => (10, digits),
+                base => (base, digits),
+            };
+            if digits.starts_with(['+', '-']) {
+                return None;
+            }
+            i128::from_str_radix(digits, base)
+                .ok()
+                .map(|x| Value::from(if neg { -x } else { x }))
+        }
+
+        Ok(match &value.0 {
+            ValueRepr::Bool(x) => Some(Value::from(*x as u64)),
             ValueRepr::U64(_) | ValueRepr::I64(_) | ValueRepr::U128(_) | ValueRepr::I128(_) => {
-                Ok(value)
+                Some(value.clone())
             }
-            ValueRepr::F64(v) => {
-                let x = *v as i128;
-                if x as f64 == *v {
-                    Ok(Value::from(x))
-                } else {
-                    Err(Error::new(
-                        ErrorKind::InvalidOperation,
-                        format!("cannot convert float {} to integer", v),
-                    ))
-                }
+            ValueRepr::F64(v) => float_to_int(*v),
+            ValueRepr::String(s, _) => {
+                let s = s.trim();
+                parse_int(s, base).or_else(|| s.parse::<f64>().ok().and_then(float_to_int))
             }
-            ValueRepr::String(s, _) => s
-                .parse::<i128>()
-                .map(Value::from)
-                .map_err(|err| Error::new(ErrorKind::InvalidOperation, err.to_string())),
-            ValueRepr::Bytes(_)
-            | ValueRepr::Seq(_)
-            | ValueRepr::Map(_, _)
-            | ValueRepr::Dynamic(_) => Err(Error::new(
-                ErrorKind::InvalidOperation,
-                format!("cannot convert {} to integer", value.kind()),
-            )),
-            ValueRepr::Invalid(ref x) => Err(Error::new(
-                ErrorKind::InvalidOperation,
-                format!("invalid value: {}", x),
-            )),
+            ValueRepr::Invalid(ref x) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!("invalid value: {}", x),
+                ))
+            }
+            _ => None,
         }
+        .unwrap_or(default))
     }
 
     /// Converts a value into a float.
     ///
+    /// Strings are parsed as floats and booleans become `0.0` or `1.0`.  If
+    /// the value cannot be converted the `default` (which defaults to `0.0`)
+    /// is returned.
+    ///
     /// ```jinja
     /// {{ "42.5"|float == 42.5 }} -> true
+    /// {{ "nope"|float(-1.0) }} -> -1.0
     /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
-    pub fn float(value: Value) -> Result<Value, Error> {
-        match &value.0 {
-            ValueRepr::Undefined | ValueRepr::None => Ok(Value::from(0.0)),
-            ValueRepr::Bool(x) => Ok(Value::from(*x as u64 as f64)),
-            ValueRepr::String(s, _) => s
-                .parse::<f64>()
-                .map(Value::from)
-                .map_err(|err| Error::new(ErrorKind::InvalidOperation, err.to_string())),
-            ValueRepr::Invalid(ref x) => Err(Error::new(
-                ErrorKind::InvalidOperation,
-                format!("invalid value: {}", x),
-            )),
-            _ => as_f64(&value).map(Value::from).ok_or_else(|| {
-                Error::new(
+    pub fn float(value: Value, default: Option<Value>, kwargs: Kwargs) -> Result<Value, Error> {
+        let default = match default {
+            Some(default) => default,
+            None => ok!(kwargs.get::<Option<Value>>("default")).unwrap_or(Value::from(0.0)),
+        };
+        ok!(kwargs.assert_all_used());
+        Ok(match &value.0 {
+            ValueRepr::Bool(x) => Some(Value::from(*x as u64 as f64)),
+            ValueRepr::String(s, _) => s.trim().parse::<f64>().ok().map(Value::from),
+            ValueRepr::Invalid(ref x) => {
+                return Err(Error::new(
                     ErrorKind::InvalidOperation,
-                    format!("cannot convert {} to float", value.kind()),
-                )
-            }),
+                    format!("invalid value: {}", x),
+                ))
+            }
+            _ => as_f64(&value).map(Value::from),
         }
+        .unwrap_or(default))
     }
 
     /// Looks up an attribute.
     ///
-    /// In MiniJinja this is the same as the `[]` operator.  In Jinja2 there is a
-    /// small difference which is why this filter is sometimes used in Jinja2
-    /// templates.  For compatibility it's provided here as well.
+    /// This is useful when the name of the attribute is computed at runtime.
+    /// If the attribute does not exist `undefined` is returned.
+    ///
+    /// In Jinja2 the filter differs from the `[]` operator in that it only looks
+    /// up attributes of the Python object and never items of a mapping.  MiniJinja
+    /// does not have this distinction: the attributes of a map are its keys and
+    /// the fields of a struct object are its attributes, so in MiniJinja this
+    /// is the same as the `[]` operator.  The filter is provided for
+    /// compatibility with Jinja2 templates.
     ///
     /// ```jinja
     /// {{ value['key'] == value|attr('key') }} -> true
+    /// {{ user|attr(field ~ '_name') }}
     /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn attr(value: Value, key: &Value) -> Result<Value, Error> {
         value.get_item(key)
     }
 
+    /// Returns the name of the variant of a serialized enum.
+    ///
+    /// Rust enums passed to the engine via serde are represented according to
+    /// their serde enum representation.  This filter extracts the name of the
+    /// variant so that templates can branch on it.  The following
+    /// representations are supported:
+    ///
+    /// * externally tagged (the default): unit variants are represented as
+    ///   string, all other variants as a map with a single key which is the
+    ///   name of the variant.  This is what is used if no `tag` is given.
+    /// * internally and adjacently tagged (`#[serde(tag = "...")]`): the
+    ///   variant name is stored in the given `tag` key which must be passed
+    ///   to the filter as keyword argument.
+    ///
+    /// Untagged enums do not retain the variant name and cannot be supported.
+    ///
+    /// ```jinja
+    /// {% if shape|enum_variant == "Circle" %}
+    ///   radius: {{ shape.Circle.radius }}
+    /// {% endif %}
+    /// {{ event|enum_variant(tag="type") }}
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
+    pub fn enum_variant(value: Value, kwargs: Kwargs) -> Result<Value, Error> {
+        let tag = ok!(kwargs.get::<Option<Cow<'_, str>>>("tag"));
+        ok!(kwargs.assert_all_used());
+
+        if let Some(tag) = tag {
+            return match value.get_attr(&tag) {
+                Ok(variant) if variant.kind() == ValueKind::String => Ok(variant),
+                _ => Err(Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!("enum tag {tag:?} not found in value"),
+                )),
+            };
+        }
+
+        match value.kind() {
+            ValueKind::String => return Ok(value),
+            ValueKind::Map if value.len() == Some(1) => {
+                if let Some(variant) = ok!(value.try_iter()).next() {
+                    return Ok(variant);
+                }
+            }
+            _ => {}
+        }
+        Err(Error::new(
+            ErrorKind::InvalidOperation,
+            format!(
+                "cannot get enum variant of value of type {}, expected externally tagged enum",
+                value.kind()
+            ),
+        ))
+    }
+
     /// Round the number to a given precision.
     ///
     /// Round the number to a given precision. The first parameter specifies the
-    /// precision (default is 0).
+    /// precision (default is 0), the second the rounding method:
+    ///
+    /// - `common` rounds either up or down (default)
+    /// - `ceil` always rounds up
+    /// - `floor` always rounds down
+    ///
+    /// Integers are passed through unchanged.
     ///
     /// ```jinja
     /// {{ 42.55|round }}
     ///   -> 43.0
+    /// {{ 42.55|round(1, 'floor') }}
+    ///   -> 42.5
     /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
-    pub fn round(value: Value, precision: Option<i32>) -> Result<Value, Error> {
+    pub fn round(
+        value: Value,
+        precision: Option<i32>,
+        method: Option<&str>,
+    ) -> Result<Value, Error> {
+        let round_func: fn(f64) -> f64 = match method.unwrap_or("common") {
+            "common" => f64::round,
+            "ceil" => f64::ceil,
+            "floor" => f64::floor,
+            other => {
+                return Err(Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!("invalid rounding method '{}'", other),
+                ))
+            }
+        };
         match value.0 {
             ValueRepr::I64(_) | ValueRepr::I128(_) | ValueRepr::U64(_) | ValueRepr::U128(_) => {
                 Ok(value)
             }
             ValueRepr::F64(val) => {
                 let x = 10f64.powi(precision.unwrap_or(0));
-                Ok(Value::from((x * val).round() / x))
+                Ok(Value::from(round_func(x * val) / x))
             }
             _ => Err(Error::new(
                 ErrorKind::InvalidOperation,
@@ -693,8 +1239,13 @@ mod builtins {
     pub fn first(value: Value) -> Result<Value, Error> {
         if let Some(s) = value.as_str() {
             Ok(s.chars().next().map_or(Value::UNDEFINED, Value::from))
-        } else if let Some(s) = value.as_seq() {
-            Ok(s.get_item(0).unwrap_or(Value::UNDEFINED))
+        } else if let Some(seq) = value.as_seq() {
+            Ok(if seq.item_count() > 0 {
+                seq.get_item(0)
+            } else {
+                None
+            }
+            .unwrap_or(Value::UNDEFINED))
         } else {
             Err(Error::new(
                 ErrorKind::InvalidOperation,
@@ -723,7 +1274,11 @@ mod builtins {
         if let Some(s) = value.as_str() {
             Ok(s.chars().next_back().map_or(Value::UNDEFINED, Value::from))
         } else if let Some(seq) = value.as_seq() {
-            Ok(seq.iter().last().unwrap_or(Value::UNDEFINED))
+            Ok(seq
+                .item_count()
+                .checked_sub(1)
+                .and_then(|idx| seq.get_item(idx))
+                .unwrap_or(Value::UNDEFINED))
         } else {
             Err(Error::new(
                 ErrorKind::InvalidOperation,
@@ -738,7 +1293,7 @@ mod builtins {
         let iter = ok!(state.undefined_behavior().try_iter(value).map_err(|err| {
             Error::new(ErrorKind::InvalidOperation, "cannot convert value to list").with_source(err)
         }));
-        Ok(iter.min().unwrap_or(Value::UNDEFINED))
+        Ok(iter.min_by(ops::cmp).unwrap_or(Value::UNDEFINED))
     }
 
     /// Returns the largest item from the list.
@@ -747,7 +1302,50 @@ mod builtins {
         let iter = ok!(state.undefined_behavior().try_iter(value).map_err(|err| {
             Error::new(ErrorKind::InvalidOperation, "cannot convert value to list").with_source(err)
         }));
-        Ok(iter.max().unwrap_or(Value::UNDEFINED))
+        Ok(iter.max_by(ops::cmp).unwrap_or(Value::UNDEFINED))
+    }
+
+    /// Returns the sum of a sequence of numbers.
+    ///
+    /// The filter accepts a few keyword arguments:
+    ///
+    /// * `attribute`: can be set to an attribute or dotted path to sum up
+    ///   that attribute of each item.
+    /// * `start`: the value to start with.  Defaults to `0`.
+    ///
+    /// Non-numeric items result in an error.
+    ///
+    /// ```jinja
+    /// Total: {{ items|sum(attribute="price") }}
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
+    pub fn sum(state: &State, value: Value, kwargs: Kwargs) -> Result<Value, Error> {
+        let iter = ok!(state.undefined_behavior().try_iter(value).map_err(|err| {
+            Error::new(ErrorKind::InvalidOperation, "cannot convert value to list").with_source(err)
+        }));
+        let attr = ok!(kwargs.get::<Option<&str>>("attribute"));
+        let mut rv = ok!(kwargs.get::<Option<Value>>("start")).unwrap_or(Value::from(0));
+        if rv.kind() != ValueKind::Number {
+            return Err(Error::new(
+                ErrorKind::InvalidOperation,
+                "start value of sum must be a number",
+            ));
+        }
+        ok!(kwargs.assert_all_used());
+        for item in iter {
+            let item = match attr {
+                Some(attr) => ok!(item.get_path(attr)),
+                None => item,
+            };
+            if item.kind() != ValueKind::Number {
+                return Err(Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!("cannot sum value of type {}", item.kind()),
+                ));
+            }
+            rv = ok!(crate::value::ops::add(&rv, &item));
+        }
+        Ok(rv)
     }
 
     /// Returns the sorted version of the given list.
@@ -938,31 +1536,47 @@ mod builtins {
     /// </script>
     /// <a href="#" data-info='{{ json_object|tojson }}'>...</a>
     /// ```
+    ///
+    /// JSON cannot represent `NaN` or infinity.  By default serializing such a
+    /// float fails, this can be changed with
+    /// [`Environment::set_json_float_policy`](crate::Environment::set_json_float_policy).
     #[cfg_attr(docsrs, doc(cfg(all(feature = "builtins", feature = "json"))))]
     #[cfg(feature = "json")]
-    pub fn tojson(value: Value, pretty: Option<bool>) -> Result<Value, Error> {
-        if pretty.unwrap_or(false) {
-            serde_json::to_string_pretty(&value)
-        } else {
-            serde_json::to_string(&value)
-        }
-        .map_err(|err| {
-            Error::new(ErrorKind::InvalidOperation, "cannot serialize to JSON").with_source(err)
-        })
-        .map(|s| {
-            // When this filter is used the return value is safe for both HTML and JSON
-            let mut rv = String::with_capacity(s.len());
-            for c in s.chars() {
-                match c {
-                    '<' => rv.push_str("\\u003c"),
-                    '>' => rv.push_str("\\u003e"),
-                    '&' => rv.push_str("\\u0026"),
-                    '\'' => rv.push_str("\\u0027"),
-                    _ => rv.push(c),
+    pub fn tojson(state: &State, value: Value, pretty: Option<bool>) -> Result<Value, Error> {
+        state
+            .env()
+            .json_float_policy()
+            .to_json_string(&value, pretty.unwrap_or(false))
+            .map(|s| {
+                // When this filter is used the return value is safe for both HTML and JSON
+                let mut rv = String::with_capacity(s.len());
+                for c in s.chars() {
+                    match c {
+                        '<' => rv.push_str("\\u003c"),
+                        '>' => rv.push_str("\\u003e"),
+                        '&' => rv.push_str("\\u0026"),
+                        '\'' => rv.push_str("\\u0027"),
+                        _ => rv.push(c),
+                    }
                 }
-            }
-            Value::from_safe_string(rv)
-        })
+                Value::from_safe_string(rv)
+            })
+    }
+
+    /// Parses a JSON string into a value.
+    ///
+    /// This filter is only available if the `json` feature is enabled.  It's
+    /// the inverse of [`tojson`] and is useful for templates that are given
+    /// JSON encoded data as a string.  Invalid JSON results in an error.
+    ///
+    /// ```jinja
+    /// {% set config = raw_config|fromjson %}
+    /// <p>Theme: {{ config.theme }}</p>
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "builtins", feature = "json"))))]
+    #[cfg(feature = "json")]
+    pub fn fromjson(value: &str) -> Result<Value, Error> {
+        Value::from_json_str(value)
     }
 
     /// indents Value with spaces
@@ -980,6 +1594,16 @@ mod builtins {
     /// {{ global_config|indent(2,true) }}     # indent whole Value with two spaces
     /// {{ global_config|indent(2,true,true)}} # indent whole Value and all blank lines
     /// ```
+    ///
+    /// Additionally the `skip_empty` keyword argument can be set to `true` to
+    /// not indent lines that only consist of whitespace.  Such lines are left
+    /// as they are.  Unlike the blank lines parameter, which only controls
+    /// lines that are completely empty, this makes sure that no trailing
+    /// whitespace is added to lines that look empty:
+    ///
+    /// ```jinja
+    /// {{ global_config|indent(2, skip_empty=true) }}
+    /// ```
     #[cfg_attr(docsrs, doc(cfg(all(feature = "builtins"))))]
     #[cfg(feature = "builtins")]
     pub fn indent(
@@ -987,7 +1611,8 @@ mod builtins {
         width: usize,
         indent_first_line: Option<bool>,
         indent_blank_lines: Option<bool>,
-    ) -> String {
+        kwargs: Kwargs,
+    ) -> Result<String, Error> {
         fn strip_trailing_newline(input: &mut String) {
             if input.ends_with('\n') {
                 input.truncate(input.len() - 1);
@@ -997,6 +1622,9 @@ mod builtins {
             }
         }
 
+        let skip_empty = ok!(kwargs.get::<Option<bool>>("skip_empty")).unwrap_or(false);
+        ok!(kwargs.assert_all_used());
+
         strip_trailing_newline(&mut value);
         let indent_with = " ".repeat(width);
         let mut output = String::new();
@@ -1010,13 +1638,65 @@ mod builtins {
                 if indent_blank_lines.unwrap_or(false) {
                     output.push_str(&indent_with);
                 }
+            } else if skip_empty && line.trim().is_empty() {
+                output.push_str(line);
             } else {
                 write!(output, "{}{}", indent_with, line).ok();
             }
             output.push('\n');
         }
         strip_trailing_newline(&mut output);
-        output
+        Ok(output)
+    }
+
+    /// Removes the common leading whitespace from all lines.
+    ///
+    /// This works like Python's `textwrap.dedent`: the longest whitespace
+    /// prefix shared by all lines is removed.  Lines that consist only of
+    /// whitespace are ignored for computing the prefix and are normalized to
+    /// empty lines.  Tabs and spaces are not considered equal.
+    ///
+    /// ```jinja
+    /// <pre>{{ snippet|dedent }}</pre>
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
+    pub fn dedent(value: Cow<'_, str>) -> String {
+        fn is_blank(line: &str) -> bool {
+            line.trim_matches(|c| c == ' ' || c == '\t' || c == '\r')
+                .is_empty()
+        }
+
+        let mut margin: Option<&str> = None;
+        for line in value.split('\n').filter(|line| !is_blank(line)) {
+            let indent = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+            margin = Some(match margin {
+                None => indent,
+                Some(margin) => {
+                    let common = margin
+                        .bytes()
+                        .zip(indent.bytes())
+                        .take_while(|(a, b)| a == b)
+                        .count();
+                    &margin[..common]
+                }
+            });
+        }
+        let margin = margin.unwrap_or("");
+
+        let mut rv = String::with_capacity(value.len());
+        for (idx, line) in value.split('\n').enumerate() {
+            if idx > 0 {
+                rv.push('\n');
+            }
+            if is_blank(line) {
+                if line.ends_with('\r') {
+                    rv.push('\r');
+                }
+            } else {
+                rv.push_str(&line[margin.len()..]);
+            }
+        }
+        rv
     }
 
     /// URL encodes a value.
@@ -1069,6 +1749,115 @@ mod builtins {
         }
     }
 
+    /// Formats a map as HTML/XML attributes.
+    ///
+    /// Every key and value pair is rendered as `key="value"` with the value
+    /// HTML escaped.  Pairs where the value is none or undefined are skipped.
+    /// By default the result starts with a space so that it can be placed
+    /// right after the tag name, this can be disabled with `autospace=false`.
+    /// Keys containing whitespace or any of `/`, `>` and `=` are rejected.
+    ///
+    /// ```jinja
+    /// <ul{{ {"class": "my_list", "missing": none, "id": "list-" ~ variable}|xmlattr }}>
+    /// ...
+    /// </ul>
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
+    pub fn xmlattr(value: Value, kwargs: Kwargs) -> Result<Value, Error> {
+        let autospace = ok!(kwargs.get::<Option<bool>>("autospace")).unwrap_or(true);
+        ok!(kwargs.assert_all_used());
+        if value.kind() != ValueKind::Map {
+            return Err(Error::new(
+                ErrorKind::InvalidOperation,
+                "xmlattr filter requires a map",
+            ));
+        }
+
+        let mut rv = String::new();
+        for k in ok!(value.try_iter()) {
+            let v = ok!(value.get_item(&k));
+            if v.is_none() || v.is_undefined() {
+                continue;
+            }
+            let key = k.to_string();
+            if key.is_empty()
+                || key
+                    .chars()
+                    .any(|c| c.is_whitespace() || c == '/' || c == '>' || c == '=')
+            {
+                return Err(Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!("invalid character in attribute name: {:?}", key),
+                ));
+            }
+            if !rv.is_empty() || autospace {
+                rv.push(' ');
+            }
+            if v.is_safe() {
+                write!(rv, "{}=\"{}\"", key, v).unwrap();
+            } else {
+                write!(rv, "{}=\"{}\"", key, HtmlEscape(&v.to_string())).unwrap();
+            }
+        }
+        Ok(Value::from_safe_string(rv))
+    }
+
+    /// Renders a markdown string to HTML.
+    ///
+    /// The input is parsed as [CommonMark](https://commonmark.org/) and the
+    /// resulting HTML is returned as safe string so that it's not escaped
+    /// again.  This filter is only available if the `markdown` feature is
+    /// enabled.
+    ///
+    /// By default the output is sanitized: raw HTML in the input is escaped
+    /// rather than passed through and links or images pointing to
+    /// `javascript:`, `vbscript:` or `data:` URLs are neutralized.  This can
+    /// be turned off by passing `sanitize=false`.
+    ///
+    /// **Security note:** with `sanitize=false` any HTML contained in the
+    /// markdown source ends up in the output verbatim.  Never disable
+    /// sanitization for untrusted input as this opens the door to XSS attacks.
+    /// Note that the built-in sanitization is only concerned with raw HTML and
+    /// URLs, it is not a replacement for a full HTML sanitizer.
+    ///
+    /// ```jinja
+    /// <article>{{ post.body|markdown }}</article>
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "builtins", feature = "markdown"))))]
+    #[cfg(feature = "markdown")]
+    pub fn markdown(value: Cow<'_, str>, kwargs: Kwargs) -> Result<Value, Error> {
+        use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+
+        fn is_unsafe_url(url: &str) -> bool {
+            let url = url.trim_start().to_ascii_lowercase();
+            ["javascript:", "vbscript:", "data:"]
+                .iter()
+                .any(|scheme| url.starts_with(scheme))
+        }
+
+        let sanitize = ok!(kwargs.get::<Option<bool>>("sanitize")).unwrap_or(true);
+        ok!(kwargs.assert_all_used());
+
+        let mut rv = String::with_capacity(value.len() * 3 / 2);
+        let parser = Parser::new_ext(&value, Options::empty()).map(|event| {
+            if !sanitize {
+                return event;
+            }
+            match event {
+                Event::Html(html) => Event::Text(html),
+                Event::Start(Tag::Link(ty, url, title)) if is_unsafe_url(&url) => {
+                    Event::Start(Tag::Link(ty, CowStr::Borrowed(""), title))
+                }
+                Event::Start(Tag::Image(ty, url, title)) if is_unsafe_url(&url) => {
+                    Event::Start(Tag::Image(ty, CowStr::Borrowed(""), title))
+                }
+                other => other,
+            }
+        });
+        html::push_html(&mut rv, parser);
+        Ok(Value::from_safe_string(rv))
+    }
+
     #[cfg(feature = "builtins")]
     fn select_or_reject(
         state: &State,
@@ -55,7 +55,23 @@
 //! If a function wants to disambiguate between a value passed as keyword argument or not,
 //! the [`Value::is_kwargs`] can be used which returns `true` if a value represents
 //! keyword arguments as opposed to just a map.  A more convenient way to work with keyword
-//! arguments is the [`Kwargs`](crate::value::Kwargs) type.
+//! arguments is the [`Kwargs`](crate::value::Kwargs) type.  It can be declared as
+//! the last parameter of a function, optionally after a leading [`&State`](State):
+//!
+//! ```rust
+//! # use minijinja::Environment;
+//! # let mut env = Environment::new();
+//! use minijinja::{Error, State};
+//! use minijinja::value::Kwargs;
+//!
+//! fn greet(_state: &State, name: &str, kwargs: Kwargs) -> Result<String, Error> {
+//!     let greeting = kwargs.get::<Option<&str>>("greeting")?.unwrap_or("Hello");
+//!     kwargs.assert_all_used()?;
+//!     Ok(format!("{greeting} {name}!"))
+//! }
+//!
+//! env.add_function("greet", greet);
+//! ```
 //!
 //! # Built-in Functions
 //!
@@ -231,8 +247,13 @@ impl Object for BoxedFunction {
 mod builtins {
     use super::*;
 
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
     use crate::error::ErrorKind;
-    use crate::value::{MapType, ObjectKind, Rest, ValueMap, ValueRepr};
+    use crate::value::namespace_object::Namespace;
+    use crate::value::{
+        from_args, MapType, ObjectKind, Rest, SeqObject, StructObject, ValueMap, ValueRepr,
+    };
 
     /// Returns a range.
     ///
@@ -240,6 +261,7 @@ mod builtins {
     /// j)` returns `[i, i+1, i+2, ..., j-1]`. `lower` defaults to 0. When `step` is
     /// given, it specifies the increment (or decrement). For example, `range(4)`
     /// and `range(0, 4, 1)` return `[0, 1, 2, 3]`. The end point is omitted.
+    /// A negative step counts down: `range(5, 0, -1)` returns `[5, 4, 3, 2, 1]`.
     ///
     /// ```jinja
     /// <ul>
@@ -251,36 +273,58 @@ mod builtins {
     ///
     /// This function will refuse to create ranges over 10.000 items.
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
-    pub fn range(lower: u32, upper: Option<u32>, step: Option<u32>) -> Result<Vec<u32>, Error> {
-        fn to_result<I: ExactSizeIterator<Item = u32>>(i: I) -> Result<Vec<u32>, Error> {
-            if i.len() > 10000 {
-                Err(Error::new(
-                    ErrorKind::InvalidOperation,
-                    "range has too many elements",
-                ))
-            } else {
-                Ok(i.collect())
-            }
-        }
-
-        let rng = match upper {
-            Some(upper) => lower..upper,
-            None => 0..lower,
+    pub fn range(lower: i64, upper: Option<i64>, step: Option<i64>) -> Result<Vec<i64>, Error> {
+        let (start, stop) = match upper {
+            Some(upper) => (lower, upper),
+            None => (0, lower),
         };
-        if let Some(step) = step {
-            if step == 0 {
-                Err(Error::new(
+        let step = step.unwrap_or(1);
+        let len = match step {
+            0 => {
+                return Err(Error::new(
                     ErrorKind::InvalidOperation,
                     "cannot create range with step of 0",
                 ))
-            } else {
-                to_result(rng.step_by(step as usize))
             }
+            step if step > 0 && start < stop => {
+                (stop as i128 - start as i128 + step as i128 - 1) / step as i128
+            }
+            step if step < 0 && start > stop => {
+                (start as i128 - stop as i128 - step as i128 - 1) / -(step as i128)
+            }
+            _ => 0,
+        };
+        if len > 10000 {
+            Err(Error::new(
+                ErrorKind::InvalidOperation,
+                "range has too many elements",
+            ))
         } else {
-            to_result(rng)
+            Ok((0..len as i64).map(|idx| start + idx * step).collect())
         }
     }
 
+    fn map_from_pairs(seq: &dyn SeqObject) -> Result<ValueMap, Error> {
+        let mut rv = ValueMap::default();
+        for pair in seq.iter() {
+            match pair.as_seq() {
+                Some(pair) if pair.item_count() == 2 => {
+                    rv.insert(
+                        crate::value::KeyRef::Value(pair.get_item(0).unwrap_or_default()),
+                        pair.get_item(1).unwrap_or_default(),
+                    );
+                }
+                _ => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidOperation,
+                        "dict() requires a sequence of key-value pairs",
+                    ))
+                }
+            }
+        }
+        Ok(rv)
+    }
+
     /// Creates a dictionary.
     ///
     /// This is a convenient alternative for a dictionary literal.
@@ -299,6 +343,12 @@ mod builtins {
     /// ```jinja
     /// {% set new_dict = dict(old_dict, extra_value=2) %}
     /// ```
+    ///
+    /// Instead of a map a sequence of key-value pairs can be passed as well:
+    ///
+    /// ```jinja
+    /// {% set lookup = dict([["a", 1], ["b", 2]]) %}
+    /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn dict(value: Option<Value>, update_with: crate::value::Kwargs) -> Result<Value, Error> {
         let mut rv = match value {
@@ -306,9 +356,10 @@ mod builtins {
             Some(value) => match value.0 {
                 ValueRepr::Undefined => Arc::new(ValueMap::default()),
                 ValueRepr::Map(map, _) => map,
+                ValueRepr::Seq(ref seq) => Arc::new(ok!(map_from_pairs(&**seq))),
                 ValueRepr::Dynamic(ref dynamic) => match dynamic.kind() {
                     ObjectKind::Plain => Arc::new(ValueMap::default()),
-                    ObjectKind::Seq(_) => return Err(Error::from(ErrorKind::InvalidOperation)),
+                    ObjectKind::Seq(seq) => Arc::new(ok!(map_from_pairs(seq))),
                     ObjectKind::Struct(s) => {
                         let mut rv = ValueMap::default();
                         for field in s.fields() {
@@ -335,12 +386,159 @@ mod builtins {
         Ok(Value(ValueRepr::Map(rv, MapType::Normal)))
     }
 
+    /// Returns the first argument that is neither undefined nor none.
+    ///
+    /// If all arguments are undefined or none, the last argument is returned.
+    /// This is a more compact alternative to chaining multiple `default`
+    /// filters.
+    ///
+    /// ```jinja
+    /// <p>Hello {{ coalesce(user.nickname, user.name, "Anonymous") }}!</p>
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
+    pub fn coalesce(args: Rest<Value>) -> Value {
+        args.iter()
+            .find(|x| !x.is_undefined() && !x.is_none())
+            .or_else(|| args.last())
+            .cloned()
+            .unwrap_or(Value::UNDEFINED)
+    }
+
+    /// Creates a cycler that cycles through the given values.
+    ///
+    /// This works like `loop.cycle` but is independent of any loop.  Calling
+    /// `next()` returns the current item and advances to the next one, the
+    /// `current` attribute returns the current item without advancing and
+    /// `reset()` goes back to the first item.
+    ///
+    /// ```jinja
+    /// {% set row_class = cycler("odd", "even") %}
+    /// {% for user in users %}
+    ///   <li class="{{ row_class.next() }}">{{ user }}</li>
+    /// {% endfor %}
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
+    pub fn cycler(items: Rest<Value>) -> Result<Value, Error> {
+        if items.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidOperation,
+                "cycler requires at least one item",
+            ));
+        }
+        Ok(Value::from_object(Cycler {
+            items: items.0,
+            pos: AtomicUsize::new(0),
+        }))
+    }
+
+    #[derive(Debug)]
+    struct Cycler {
+        items: Vec<Value>,
+        pos: AtomicUsize,
+    }
+
+    impl Cycler {
+        fn current(&self) -> Value {
+            self.items[self.pos.load(Ordering::Relaxed)].clone()
+        }
+    }
+
+    impl fmt::Display for Cycler {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(
+                f,
+                "<cycler {}/{}>",
+                self.pos.load(Ordering::Relaxed),
+                self.items.len()
+            )
+        }
+    }
+
+    impl Object for Cycler {
+        fn kind(&self) -> ObjectKind<'_> {
+            ObjectKind::Struct(self)
+        }
+
+        fn call_method(&self, _state: &State, name: &str, args: &[Value]) -> Result<Value, Error> {
+            let _: () = ok!(from_args(args));
+            match name {
+                "next" => {
+                    let len = self.items.len();
+                    let pos = self
+                        .pos
+                        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |pos| {
+                            Some((pos + 1) % len)
+                        })
+                        .unwrap();
+                    Ok(self.items[pos].clone())
+                }
+                "reset" => {
+                    self.pos.store(0, Ordering::Relaxed);
+                    Ok(Value::from(()))
+                }
+                _ => Err(Error::new(
+                    ErrorKind::UnknownMethod,
+                    format!("cycler has no method named {name}"),
+                )),
+            }
+        }
+    }
+
+    impl StructObject for Cycler {
+        fn get_field(&self, name: &str) -> Option<Value> {
+            match name {
+                "current" => Some(self.current()),
+                _ => None,
+            }
+        }
+
+        fn static_fields(&self) -> Option<&'static [&'static str]> {
+            Some(&["current"][..])
+        }
+    }
+
+    /// Creates a namespace object.
+    ///
+    /// A namespace is a mutable object whose attributes can be assigned to
+    /// with the `{% set %}` tag.  As assignments in loops do not leave the
+    /// loop, this can be used to carry values out of them.  The initial
+    /// attributes can be passed as a map, keyword arguments or both.
+    ///
+    /// ```jinja
+    /// {% set ns = namespace(total=0) %}
+    /// {% for item in items %}{% set ns.total += item.price %}{% endfor %}
+    /// Total: {{ ns.total }}
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
+    pub fn namespace(
+        defaults: Option<Value>,
+        kwargs: crate::value::Kwargs,
+    ) -> Result<Value, Error> {
+        let ns = Namespace::default();
+        if let ValueRepr::Map(map, _) = ok!(dict(defaults, kwargs)).0 {
+            for (key, value) in map.iter() {
+                match key.as_str() {
+                    Some(key) => ns.set_field(key, value.clone()),
+                    None => {
+                        return Err(Error::new(
+                            ErrorKind::InvalidOperation,
+                            "namespace attributes must be strings",
+                        ))
+                    }
+                }
+            }
+        }
+        Ok(Value::from_object(ns))
+    }
+
     /// Outputs the current context or the arguments stringified.
     ///
     /// This is a useful function to quickly figure out the state of affairs
     /// in a template.  It emits a stringified debug dump of the current
     /// engine state including the layers of the context, the current block
-    /// and auto escaping setting.  The exact output is not defined and might
+    /// and auto escaping setting.  The context contains all variables that are
+    /// visible at the point of the call, including loop variables and variables
+    /// created with `{% set %}`.  The exact output is not defined and might
     /// change from one version of Jinja2 to the next.
     ///
     /// ```jinja
@@ -167,10 +167,14 @@
 //! - `custom_syntax`: when this feature is enabled, custom delimiters are supported by
 //!   the parser.
 //! - `preserve_order`: When enable the internal value implementation uses an indexmap
-//!   which preserves the original order of maps and structs.
+//!   which preserves the original order of maps and structs.  See also
+//!   [`MapKeyOrder`] to force sorted iteration at runtime.
 //! - `json`: When enabled the `tojson` filter is added as builtin filter as well as
 //!   the ability to auto escape via `AutoEscape::Json`.
 //! - `urlencode`: When enabled the `urlencode` filter is added as builtin filter.
+//! - `markdown`: When enabled the `markdown` filter is added as builtin filter.
+//! - `testing`: When enabled the [`testing`] module with helpers for asserting
+//!   rendered template output in tests is available.
 //!
 //! Performance and memory related features:
 //!
@@ -231,13 +235,20 @@ mod custom_syntax;
 #[cfg(feature = "debug")]
 mod debug;
 
+#[cfg(feature = "testing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+pub mod testing;
+
 pub use self::defaults::{default_auto_escape_callback, escape_formatter};
 pub use self::environment::Environment;
 pub use self::error::{Error, ErrorKind};
 pub use self::expression::Expression;
 pub use self::output::Output;
 pub use self::template::Template;
-pub use self::utils::{AutoEscape, HtmlEscape, UndefinedBehavior};
+pub use self::utils::{AutoEscape, HtmlEscape, MapKeyOrder, UndefinedBehavior};
+
+#[cfg(feature = "json")]
+pub use self::utils::JsonFloatPolicy;
 
 /// Re-export for convenience.
 pub use self::value::Value;
@@ -260,7 +271,7 @@ pub mod machinery {
     pub use crate::compiler::ast;
     pub use crate::compiler::codegen::CodeGenerator;
     pub use crate::compiler::instructions::{Instruction, Instructions};
-    pub use crate::compiler::lexer::{tokenize, SyntaxConfig};
+    pub use crate::compiler::lexer::{tokenize, LexerConfig, SyntaxConfig};
     pub use crate::compiler::parser::{parse, parse_with_syntax};
     pub use crate::compiler::tokens::{Span, Token};
     pub use crate::template::{CompiledTemplate, TemplateConfig};
@@ -26,8 +26,9 @@ type LoadFunc = dyn for<'a> Fn(&'a str) -> Result<Option<String>, Error> + Send
 #[derive(Clone)]
 pub(crate) struct LoaderStore<'source> {
     pub template_config: TemplateConfig,
-    loader: Option<Arc<LoadFunc>>,
+    loaders: Vec<Arc<LoadFunc>>,
     owned_templates: MemoMap<Arc<str>, Arc<LoadedTemplate>>,
+    loaded_templates: MemoMap<Arc<str>, Arc<LoadedTemplate>>,
     borrowed_templates: BTreeMap<&'source str, Arc<CompiledTemplate<'source>>>,
 }
 
@@ -42,6 +43,13 @@ impl<'source> fmt::Debug for LoaderStore<'source> {
                 l.entry(key);
             }
         }
+        for key in self.loaded_templates.keys() {
+            if !self.owned_templates.contains_key(key)
+                && !self.borrowed_templates.contains_key(key as &str)
+            {
+                l.entry(key);
+            }
+        }
         l.finish()
     }
 }
@@ -72,8 +80,9 @@ impl<'source> LoaderStore<'source> {
     pub fn new(template_config: TemplateConfig) -> LoaderStore<'source> {
         LoaderStore {
             template_config,
-            loader: None,
+            loaders: Vec::new(),
             owned_templates: MemoMap::default(),
+            loaded_templates: MemoMap::default(),
             borrowed_templates: BTreeMap::default(),
         }
     }
@@ -90,6 +99,7 @@ impl<'source> LoaderStore<'source> {
         match (source, name) {
             (Cow::Borrowed(source), Cow::Borrowed(name)) => {
                 self.owned_templates.remove(name);
+                self.loaded_templates.remove(name);
                 self.borrowed_templates.insert(
                     name,
                     Arc::new(ok!(CompiledTemplate::new(
@@ -101,6 +111,7 @@ impl<'source> LoaderStore<'source> {
             }
             (source, name) => {
                 self.borrowed_templates.remove(&name as &str);
+                self.loaded_templates.remove(&name as &str);
                 let name: Arc<str> = name.into();
                 self.owned_templates.replace(
                     name.clone(),
@@ -115,26 +126,37 @@ impl<'source> LoaderStore<'source> {
     pub fn remove(&mut self, name: &str) {
         self.borrowed_templates.remove(name);
         self.owned_templates.remove(name);
+        self.loaded_templates.remove(name);
     }
 
     pub fn clear(&mut self) {
         self.borrowed_templates.clear();
         self.owned_templates.clear();
+        self.loaded_templates.clear();
+    }
+
+    pub fn clear_caches(&mut self) {
+        self.loaded_templates.clear();
     }
 
     pub fn get(&self, name: &str) -> Result<&CompiledTemplate<'_>, Error> {
         if let Some(rv) = self.borrowed_templates.get(name) {
             Ok(&**rv)
+        } else if let Some(rv) = self.owned_templates.get(name) {
+            Ok(rv.borrow_dependent())
         } else {
             let name: Arc<str> = name.into();
-            self.owned_templates
+            self.loaded_templates
                 .get_or_try_insert(&name.clone(), || -> Result<_, Error> {
-                    let loader_result = match self.loader {
-                        Some(ref loader) => ok!(loader(&name)),
-                        None => None,
+                    let mut loader_result = None;
+                    for loader in &self.loaders {
+                        loader_result = ok!(loader(&name));
+                        if loader_result.is_some() {
+                            break;
+                        }
                     }
-                    .ok_or_else(|| Error::new_not_found(&name));
-                    self.make_owned_template(name, ok!(loader_result))
+                    let source = ok!(loader_result.ok_or_else(|| Error::new_not_found(&name)));
+                    self.make_owned_template(name, source)
                 })
                 .map(|x| x.borrow_dependent())
         }
@@ -144,7 +166,14 @@ impl<'source> LoaderStore<'source> {
     where
         F: Fn(&str) -> Result<Option<String>, Error> + Send + Sync + 'static,
     {
-        self.loader = Some(Arc::new(f));
+        self.loaders = vec![Arc::new(f)];
+    }
+
+    pub fn add_loader<F>(&mut self, f: F)
+    where
+        F: Fn(&str) -> Result<Option<String>, Error> + Send + Sync + 'static,
+    {
+        self.loaders.push(Arc::new(f));
     }
 
     fn make_owned_template(
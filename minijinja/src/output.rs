@@ -140,6 +140,43 @@ impl fmt::Write for NullWriter {
     }
 }
 
+/// Collapses runs of horizontal whitespace into a single space.
+pub struct CollapseWhitespace<'a> {
+    w: &'a mut (dyn fmt::Write + 'a),
+    in_whitespace: bool,
+}
+
+impl<'a> CollapseWhitespace<'a> {
+    pub fn new(w: &'a mut (dyn fmt::Write + 'a)) -> Self {
+        CollapseWhitespace {
+            w,
+            in_whitespace: false,
+        }
+    }
+}
+
+impl fmt::Write for CollapseWhitespace<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut rest = s;
+        while let Some(start) = rest.find([' ', '\t']) {
+            if start > 0 {
+                ok!(self.w.write_str(&rest[..start]));
+                self.in_whitespace = false;
+            }
+            if !self.in_whitespace {
+                ok!(self.w.write_char(' '));
+                self.in_whitespace = true;
+            }
+            rest = rest[start..].trim_start_matches([' ', '\t']);
+        }
+        if !rest.is_empty() {
+            self.in_whitespace = false;
+            ok!(self.w.write_str(rest));
+        }
+        Ok(())
+    }
+}
+
 pub struct WriteWrapper<W> {
     pub w: W,
     pub err: Option<io::Error>,
@@ -26,6 +26,7 @@
 //!   - [`{% do %}`](#-do-)
 //!   - [`{% autoescape %}`](#-autoescape-)
 //!   - [`{% raw %}`](#-raw-)
+//! - [Line Statements and Comments](#line-statements-and-comments)
 //! - [Custom Delimiters](#custom-delimiters)
 //!
 //! </details>
@@ -142,7 +143,10 @@
 //!   `foo({"a": 1, "b": 2})`.
 //! - ``.`` / ``[]``: Get an attribute of an object.  If an object does not have a specific
 //!   attribute or item then `undefined` is returned.  Accessing a property of an already
-//!   undefined value will result in an error.
+//!   undefined value will result in an error.  Like in Jinja2 an integer after the dot
+//!   performs item access, so ``items.0`` is the same as ``items[0]``.  Map keys are not
+//!   converted, which means that a map with a string key `"0"` still needs to be accessed
+//!   as ``map["0"]``.
 //! - ``[start:stop]`` / ``[start:stop:step]``: slices a list or string.  All three expressions
 //!   are optional (`start`, `stop`, `step`).  For instance ``"Hello World"[:5]`` will return
 //!   just `"Hello"`.  Likewise ``"Hello"[1:-1]`` will return `"ell"`.  The step component can
@@ -211,6 +215,15 @@
 //! - `loop.changed(...args)`: Returns true if the passed values have changed since the last time it was called with the same arguments.
 //! - `loop.cycle(...args)`: Returns a value from the passed sequence in a cycle.
 //!
+//! Most values that can be iterated over know their length up front.  This
+//! includes dynamic [`SeqObject`](crate::value::SeqObject)s which compute
+//! their items lazily, so `loop.length`, `loop.revindex` and `loop.last` are
+//! available without buffering the items first.  Values created with
+//! [`Value::make_iterable`](crate::value::Value::make_iterable) however are
+//! streamed and for those these attributes are undefined unless
+//! [`Environment::set_loop_length_buffering`](crate::Environment::set_loop_length_buffering)
+//! is enabled.
+//!
 //! Within a for-loop, it’s possible to cycle among a list of strings/variables each time through
 //! the loop by using the special `loop.cycle` helper:
 //!
@@ -222,7 +235,10 @@
 //!
 //! A `loop.changed()` helper is also available which can be used to detect when
 //! a value changes between the last iteration and the current one.  The method
-//! takes one or more arguments that are all compared.
+//! takes one or more arguments that are all compared.  When multiple arguments
+//! are passed they are compared together as a tuple, so the method returns true
+//! if any of them changed since the last call.  Arbitrary expressions (such as
+//! nested attribute lookups) can be passed to track derived keys.
 //!
 //! ```jinja
 //! {% for entry in entries %}
@@ -231,6 +247,12 @@
 //!   {% endif %}
 //!   <p>{{ entry.message }}</p>
 //! {% endfor %}
+//!
+//! {% for entry in entries %}
+//!   {% if loop.changed(entry.meta.year, entry.meta.month) %}
+//!     <h2>{{ entry.meta.year }}-{{ entry.meta.month }}</h2>
+//!   {% endif %}
+//! {% endfor %}
 //! ```
 //!
 //! Unlike in Rust or Python, it’s not possible to break or continue in a loop. You can,
@@ -360,6 +382,13 @@
 //! this template, it first locates the parent.  The extends tag should be the
 //! first tag in the template.
 //!
+//! The name of the parent template can be any expression.  It's evaluated at
+//! render time, so the layout can be picked based on the context:
+//!
+//! ```jinja
+//! {% extends "print.html" if print_mode else layout %}
+//! ```
+//!
 //! As you can see it's also possible to render the contents of the parent block by calling
 //! ``super()``. You can’t define multiple ``{% block %}`` tags with the same name in
 //! the same template. This limitation exists because a block tag works in “both”
@@ -390,6 +419,17 @@
 //!
 //! However, the name after the `endblock` word must match the block name.
 //!
+//! Unlike in Jinja2, blocks in MiniJinja can always access variables from the
+//! surrounding scope.  For instance a block within a loop sees the loop variable
+//! and `loop` itself.  For compatibility the `scoped` modifier is accepted but
+//! has no effect:
+//!
+//! ```jinja
+//! {% for item in items %}
+//!   <li>{% block item scoped %}{{ loop.index }}: {{ item }}{% endblock %}</li>
+//! {% endfor %}
+//! ```
+//!
 //! ## `{% include %}`
 //!
 //! **Feature:** `multi_template` (included by default)
@@ -484,6 +524,24 @@
 //! and have them show up outside of it.  This also applies to loops.  The only
 //! exception to that rule are if statements which do not introduce a scope.
 //!
+//! Augmented assignments with `+=`, `-=`, `*=` and `/=` are supported as a
+//! shorthand for applying an operator to the current value of a variable:
+//!
+//! ```jinja
+//! {% set total = 0 %}
+//! {% if item.taxable %}{% set total += item.tax %}{% endif %}
+//! ```
+//!
+//! To carry values out of a loop, assign to the attributes of a
+//! `namespace()` object instead.  Both regular and augmented
+//! assignments work on namespace attributes:
+//!
+//! ```jinja
+//! {% set ns = namespace(total=0) %}
+//! {% for item in items %}{% set ns.total += item.price %}{% endfor %}
+//! total: {{ ns.total }}
+//! ```
+//!
 //! It's also possible to capture blocks of template code into a variable by using
 //! the `set` statement as a block.   In that case, instead of using an equals sign
 //! and a value, you just write the variable name and then everything until
@@ -541,6 +599,21 @@
 //! The behavior of macros with regards to undefined variables is that they capture
 //! them at macro declaration time (eg: they use a closure).
 //!
+//! Like in Jinja2 the special `loop` variable of a `{% for %}` loop is not visible
+//! inside of a macro that is called from within the loop.  If a macro needs
+//! access to the loop it has to be passed explicitly as argument.  Since `loop`
+//! is a reserved name the parameter needs a different name:
+//!
+//! ```jinja
+//! {% macro render_item(item, outer_loop) -%}
+//!   <li class="{{ outer_loop.cycle('odd', 'even') }}">{{ item }}</li>
+//! {%- endmacro %}
+//! {% for item in items %}{{ render_item(item, loop) }}{% endfor %}
+//! ```
+//!
+//! The body of a `{% call %}` block on the other hand is part of the calling
+//! template and can refer to the `loop` of the loop it is placed in.
+//!
 //! Macros can be imported via `{% import %}` or `{% from ... import %}`.
 //!
 //! Macros also accept a hidden `caller` keyword argument for the use with
@@ -684,6 +757,41 @@
 //! {% endraw %}
 //! ```
 //!
+//! The contents are not interpreted at all, so the raw block ends at the first
+//! `{% endraw %}` tag, even if it appears within what looks like a string.
+//! Whitespace control works as with other tags: `{%- raw %}` and
+//! `{% endraw -%}` trim the whitespace surrounding the block, while
+//! `{% raw -%}` and `{%- endraw %}` trim the whitespace at the beginning and
+//! end of the raw contents.
+//!
+//! # Line Statements and Comments
+//!
+//! If line statements are enabled with
+//! [`set_line_statement_prefix`](crate::Environment::set_line_statement_prefix), a
+//! line starting with the prefix is treated as a block tag.  Likewise
+//! [`set_line_comment_prefix`](crate::Environment::set_line_comment_prefix) enables
+//! comments that run until the end of the line.  With `#` and `##` as prefixes
+//! the following two templates are equivalent apart from whitespace:
+//!
+//! ```jinja
+//! <ul>
+//! # for item in seq:
+//!     <li>{{ item }}</li>  ## the current item
+//! # endfor
+//! </ul>
+//! ```
+//!
+//! ```jinja
+//! <ul>
+//! {% for item in seq %}
+//!     <li>{{ item }}</li>{# the current item #}
+//! {% endfor %}
+//! </ul>
+//! ```
+//!
+//! Line statements may be indented and consume the newline at their end while
+//! line comments keep it.
+//!
 #![cfg_attr(
     feature = "custom_syntax",
     doc = r#"
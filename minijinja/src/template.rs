@@ -1,4 +1,4 @@
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::ops::Deref;
 use std::sync::Arc;
 use std::{fmt, io};
@@ -7,12 +7,12 @@ use serde::Serialize;
 
 use crate::compiler::codegen::CodeGenerator;
 use crate::compiler::instructions::Instructions;
-use crate::compiler::lexer::SyntaxConfig;
+use crate::compiler::lexer::{LexerConfig, SyntaxConfig};
 use crate::compiler::meta::find_undeclared;
 use crate::compiler::parser::parse_with_syntax;
 use crate::environment::Environment;
 use crate::error::{attach_basic_debug_info, Error};
-use crate::output::{Output, WriteWrapper};
+use crate::output::{CollapseWhitespace, Output, WriteWrapper};
 use crate::utils::AutoEscape;
 use crate::value::{self, Value};
 use crate::vm::{prepare_blocks, Context, State, Vm};
@@ -27,8 +27,12 @@ pub struct TemplateConfig {
     pub syntax_config: SyntaxConfig,
     /// Controls the retaining of the final newline.
     pub keep_trailing_newline: bool,
+    /// Tokenizer settings such as comment retention and line prefixes.
+    pub lexer_config: LexerConfig,
     /// The callback that determines the initial auto escaping for templates.
     pub default_auto_escape: Arc<AutoEscapeFunc>,
+    /// The names of the filters that write directly into the output.
+    pub streaming_filters: Arc<BTreeSet<String>>,
 }
 
 impl TemplateConfig {
@@ -36,7 +40,9 @@ impl TemplateConfig {
         TemplateConfig {
             syntax_config: SyntaxConfig::default(),
             keep_trailing_newline: false,
+            lexer_config: LexerConfig::default(),
             default_auto_escape,
+            streaming_filters: Arc::default(),
         }
     }
 }
@@ -86,6 +92,27 @@ impl<'env, 'source> Template<'env, 'source> {
         self.compiled.instructions.source()
     }
 
+    /// Returns the names of the blocks defined in this template.
+    ///
+    /// The names are returned in sorted order and include nested blocks.
+    /// Blocks that are only defined in a template this one extends are not
+    /// included as extending is resolved at render time.  This is useful to
+    /// validate names before calling [`State::render_block`].
+    ///
+    /// ```rust
+    /// # use minijinja::Environment;
+    /// # #[cfg(feature = "multi_template")] {
+    /// let mut env = Environment::new();
+    /// env.add_template("x", "{% block title %}{% endblock %}{% block body %}{% endblock %}")
+    ///     .unwrap();
+    /// let tmpl = env.get_template("x").unwrap();
+    /// assert_eq!(tmpl.block_names(), vec!["body", "title"]);
+    /// # }
+    /// ```
+    pub fn block_names(&self) -> Vec<&str> {
+        self.compiled.blocks.keys().copied().collect()
+    }
+
     /// Renders the template into a string.
     ///
     /// The provided value is used as the initial context for the template.  It
@@ -145,8 +172,7 @@ impl<'env, 'source> Template<'env, 'source> {
 
     fn _render(&self, root: Value) -> Result<(String, State<'_, 'env>), Error> {
         let mut rv = String::with_capacity(self.compiled.buffer_size_hint);
-        self._eval(root, &mut Output::with_string(&mut rv))
-            .map(|(_, state)| (rv, state))
+        self._eval_to(root, &mut rv).map(|state| (rv, state))
     }
 
     /// Renders the template into an [`io::Write`].
@@ -173,12 +199,8 @@ impl<'env, 'source> Template<'env, 'source> {
         w: W,
     ) -> Result<State<'_, 'env>, Error> {
         let mut wrapper = WriteWrapper { w, err: None };
-        self._eval(
-            Value::from_serializable(&ctx),
-            &mut Output::with_write(&mut wrapper),
-        )
-        .map(|(_, state)| state)
-        .map_err(|err| wrapper.take_err(err))
+        self._eval_to(Value::from_serializable(&ctx), &mut wrapper)
+            .map_err(|err| wrapper.take_err(err))
     }
 
     /// Evaluates the template into a [`State`].
@@ -217,6 +239,18 @@ impl<'env, 'source> Template<'env, 'source> {
         Ok(state)
     }
 
+    fn _eval_to(&self, root: Value, w: &mut dyn fmt::Write) -> Result<State<'_, 'env>, Error> {
+        let rv = if self.env.collapse_whitespace() {
+            self._eval(
+                root,
+                &mut Output::with_write(&mut CollapseWhitespace::new(w)),
+            )
+        } else {
+            self._eval(root, &mut Output::with_write(w))
+        };
+        rv.map(|(_, state)| state)
+    }
+
     fn _eval(
         &self,
         root: Value,
@@ -256,6 +290,7 @@ impl<'env, 'source> Template<'env, 'source> {
             self.name(),
             self.compiled.syntax_config.clone(),
             true,
+            self.compiled.lexer_config.clone(),
         ) {
             Ok(ast) => find_undeclared(&ast, nested),
             Err(_) => HashSet::new(),
@@ -339,6 +374,8 @@ pub struct CompiledTemplate<'source> {
     pub buffer_size_hint: usize,
     /// The syntax config that created it.
     pub syntax_config: SyntaxConfig,
+    /// The tokenizer settings that created it.
+    pub lexer_config: LexerConfig,
     /// The initial setting of auto escaping.
     pub initial_auto_escape: AutoEscape,
 }
@@ -377,9 +414,11 @@ impl<'source> CompiledTemplate<'source> {
             source,
             name,
             config.syntax_config.clone(),
-            config.keep_trailing_newline
+            config.keep_trailing_newline,
+            config.lexer_config.clone(),
         ));
         let mut gen = CodeGenerator::new(name, source);
+        gen.set_streaming_filters(config.streaming_filters.clone());
         gen.compile_stmt(&ast);
         let buffer_size_hint = gen.buffer_size_hint();
         let (instructions, blocks) = gen.finish();
@@ -388,6 +427,7 @@ impl<'source> CompiledTemplate<'source> {
             blocks,
             buffer_size_hint,
             syntax_config: config.syntax_config.clone(),
+            lexer_config: config.lexer_config.clone(),
             initial_auto_escape: (config.default_auto_escape)(name),
         })
     }
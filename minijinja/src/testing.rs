@@ -0,0 +1,95 @@
+//! Helpers for testing templates.
+//!
+//! This module contains small utilities that are useful when writing tests
+//! for templates in downstream crates.  Rendering failures and unexpected
+//! output cause a panic with a readable report so they can be used directly
+//! in `#[test]` functions.
+//!
+//! ```
+//! use minijinja::{context, Environment};
+//! use minijinja::testing::assert_renders;
+//!
+//! let mut env = Environment::new();
+//! env.add_template("hello.txt", "Hello {{ name }}!").unwrap();
+//! assert_renders(&env, "hello.txt", context! { name => "World" }, "Hello World!");
+//! ```
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::environment::Environment;
+
+/// Renders a template for a test.
+///
+/// Looks up the template `name` in the environment and renders it with the
+/// given context.  If the template cannot be loaded or fails to render this
+/// panics with the full error including the debug information.
+///
+/// ```
+/// use minijinja::{context, Environment};
+/// use minijinja::testing::render_for_test;
+///
+/// let mut env = Environment::new();
+/// env.add_template("hello.txt", "Hello {{ name }}!").unwrap();
+/// let rv = render_for_test(&env, "hello.txt", context! { name => "World" });
+/// assert_eq!(rv, "Hello World!");
+/// ```
+#[track_caller]
+pub fn render_for_test<S: Serialize>(env: &Environment, name: &str, ctx: S) -> String {
+    match env.get_template(name).and_then(|tmpl| tmpl.render(ctx)) {
+        Ok(rv) => rv,
+        Err(err) => panic!("failed to render template {name:?}: {err:#}"),
+    }
+}
+
+/// Asserts that a template renders to the expected output.
+///
+/// This renders the template like [`render_for_test`] and compares the
+/// result with `expected`.  On mismatch it panics with a line based diff
+/// where lines prefixed with `-` are expected and lines prefixed with `+`
+/// are what the template actually rendered.
+#[track_caller]
+pub fn assert_renders<S: Serialize>(env: &Environment, name: &str, ctx: S, expected: &str) {
+    let rv = render_for_test(env, name, ctx);
+    if rv != expected {
+        panic!(
+            "template {name:?} rendered unexpected output:\n{}",
+            line_diff(expected, &rv)
+        );
+    }
+}
+
+/// Produces a simple line based diff between two strings.
+fn line_diff(expected: &str, actual: &str) -> String {
+    let old = expected.split('\n').collect::<Vec<_>>();
+    let new = actual.split('\n').collect::<Vec<_>>();
+
+    // longest common subsequence table, filled from the back
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut rv = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            writeln!(rv, "  {:?}", old[i]).ok();
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            writeln!(rv, "+ {:?}", new[j]).ok();
+            j += 1;
+        } else {
+            writeln!(rv, "- {:?}", old[i]).ok();
+            i += 1;
+        }
+    }
+    rv
+}
@@ -62,8 +62,12 @@ use crate::vm::State;
 
 type TestFunc = dyn Fn(&State, &[Value]) -> Result<bool, Error> + Sync + Send + 'static;
 
+/// A handle to a test registered with an environment.
+///
+/// Handles are returned by [`Environment::get_test`](crate::Environment::get_test)
+/// and can be used to perform a test by name.
 #[derive(Clone)]
-pub(crate) struct BoxedTest(Arc<TestFunc>);
+pub struct BoxedTest(Arc<TestFunc>);
 
 /// A utility trait that represents the return value of filters.
 ///
@@ -178,8 +182,8 @@ tuple_impls! { A B C D }
 tuple_impls! { A B C D E }
 
 impl BoxedTest {
-    /// Creates a new boxed filter.
-    pub fn new<F, Rv, Args>(f: F) -> BoxedTest
+    /// Creates a new boxed test.
+    pub(crate) fn new<F, Rv, Args>(f: F) -> BoxedTest
     where
         F: Test<Rv, Args> + for<'a> Test<Rv, <Args as FunctionArgs<'a>>::Output>,
         Rv: TestResult,
@@ -191,7 +195,9 @@ impl BoxedTest {
         }))
     }
 
-    /// Applies the filter to a value and argument.
+    /// Performs the test on a value and arguments.
+    ///
+    /// The value that is tested is the first argument.
     pub fn perform(&self, state: &State, args: &[Value]) -> Result<bool, Error> {
         (self.0)(state, args)
     }
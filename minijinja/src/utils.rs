@@ -6,6 +6,7 @@ use std::str::Chars;
 
 use crate::error::{Error, ErrorKind};
 use crate::value::{OwnedValueIterator, StringType, Value, ValueKind, ValueRepr};
+use crate::vm::State;
 use crate::Output;
 
 /// internal marker to seal up some trait methods
@@ -48,8 +49,10 @@ fn invalid_autoescape(name: &str) -> Result<(), Error> {
 }
 
 #[inline(always)]
+#[cfg_attr(not(feature = "json"), allow(unused_variables))]
 pub fn write_escaped(
     out: &mut Output,
+    state: &State,
     auto_escape: AutoEscape,
     value: &Value,
 ) -> Result<(), Error> {
@@ -65,9 +68,7 @@ pub fn write_escaped(
         AutoEscape::Html => write_with_html_escaping(out, value).map_err(Error::from),
         #[cfg(feature = "json")]
         AutoEscape::Json => {
-            let value = ok!(serde_json::to_string(&value).map_err(|err| {
-                Error::new(ErrorKind::BadSerialization, "unable to format to JSON").with_source(err)
-            }));
+            let value = ok!(state.env().json_float_policy().to_json_string(value, false));
             write!(out, "{value}").map_err(Error::from)
         }
         AutoEscape::Custom(name) => invalid_autoescape(name),
@@ -176,6 +177,114 @@ impl UndefinedBehavior {
     }
 }
 
+/// Defines the order in which the keys of maps are iterated over.
+///
+/// This affects iteration over maps in `{% for %}` loops and the `items`
+/// filter.  Filters that sort explicitly such as `dictsort` are not affected.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MapKeyOrder {
+    /// The default, iterates in the order of the map itself.
+    ///
+    /// With the `preserve_order` feature enabled this is the order in which
+    /// the keys were inserted.  Without it maps are stored sorted by key, so
+    /// this behaves like [`Sorted`](Self::Sorted) for regular maps.
+    Insertion,
+    /// Always iterates over the keys in sorted order.
+    Sorted,
+}
+
+impl Default for MapKeyOrder {
+    fn default() -> MapKeyOrder {
+        MapKeyOrder::Insertion
+    }
+}
+
+impl MapKeyOrder {
+    /// Returns a value that iterates over the keys of a map in this order.
+    ///
+    /// Values that are not maps are returned unchanged.
+    pub(crate) fn apply(self, value: Value) -> Value {
+        match (self, value.kind()) {
+            (MapKeyOrder::Sorted, ValueKind::Map) => match value.try_iter() {
+                Ok(iter) => {
+                    let mut keys = iter.collect::<Vec<_>>();
+                    keys.sort();
+                    Value::from(keys)
+                }
+                Err(_) => value,
+            },
+            _ => value,
+        }
+    }
+}
+
+/// Defines how non-finite floats are serialized to JSON.
+///
+/// JSON has no representation for `NaN` or infinity.  This controls what
+/// happens when such a value is encountered by the `tojson` filter, by
+/// [`AutoEscape::Json`] or by [`Value::to_json_string`].  For more
+/// information see
+/// [`set_json_float_policy`](crate::Environment::set_json_float_policy).
+#[cfg(feature = "json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum JsonFloatPolicy {
+    /// The default, fails serialization with an error.
+    Error,
+    /// Serializes non-finite floats as `null`.
+    Null,
+}
+
+#[cfg(feature = "json")]
+impl Default for JsonFloatPolicy {
+    fn default() -> JsonFloatPolicy {
+        JsonFloatPolicy::Error
+    }
+}
+
+#[cfg(feature = "json")]
+impl JsonFloatPolicy {
+    /// Serializes a value to JSON according to this policy.
+    pub(crate) fn to_json_string(self, value: &Value, pretty: bool) -> Result<String, Error> {
+        if matches!(self, JsonFloatPolicy::Error) && contains_non_finite_float(value) {
+            return Err(Error::new(
+                ErrorKind::BadSerialization,
+                "cannot serialize non-finite float (NaN or infinity) to JSON",
+            ));
+        }
+        if pretty {
+            serde_json::to_string_pretty(value)
+        } else {
+            serde_json::to_string(value)
+        }
+        .map_err(|err| {
+            Error::new(ErrorKind::BadSerialization, "cannot serialize to JSON").with_source(err)
+        })
+    }
+}
+
+#[cfg(feature = "json")]
+fn contains_non_finite_float(value: &Value) -> bool {
+    use crate::value::ObjectKind;
+
+    match value.0 {
+        ValueRepr::F64(f) => !f.is_finite(),
+        ValueRepr::Seq(ref items) => items.iter().any(contains_non_finite_float),
+        ValueRepr::Map(ref items, _) => items.values().any(contains_non_finite_float),
+        ValueRepr::Dynamic(ref dy) => match dy.kind() {
+            ObjectKind::Plain => false,
+            ObjectKind::Seq(s) => s.iter().any(|item| contains_non_finite_float(&item)),
+            ObjectKind::Struct(s) => s.fields().iter().any(|field| {
+                s.get_field(field)
+                    .map_or(false, |value| contains_non_finite_float(&value))
+            }),
+        },
+        _ => false,
+    }
+}
+
 /// Helper to HTML escape a string.
 pub struct HtmlEscape<'a>(pub &'a str);
 
@@ -331,6 +440,38 @@ impl<F: FnOnce()> Drop for OnDrop<F> {
     }
 }
 
+/// Computes the levenshtein distance between two strings.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = prev + (ca != *cb) as usize;
+            prev = row[j + 1];
+            row[j + 1] = cost.min(row[j] + 1).min(prev + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Finds the candidate most similar to the given name.
+///
+/// Only candidates that are reasonably close are considered.
+pub fn find_similar<'a, I: IntoIterator<Item = &'a str>>(
+    name: &str,
+    candidates: I,
+) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .map(|candidate| (levenshtein(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -351,4 +492,15 @@ mod tests {
         assert_eq!(unescape("foobarbaz").unwrap(), "foobarbaz");
         assert_eq!(unescape(r"\ud83d\udca9").unwrap(), "💩");
     }
+
+    #[test]
+    fn test_find_similar() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("upper", "upper"), 0);
+        let candidates = ["lower", "upper", "title", "trim"];
+        assert_eq!(find_similar("uper", candidates), Some("upper"));
+        assert_eq!(find_similar("trm", candidates), Some("trim"));
+        assert_eq!(find_similar("completely_different", candidates), None);
+    }
 }
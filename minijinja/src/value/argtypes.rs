@@ -18,6 +18,11 @@ use crate::vm::State;
 ///
 /// * `Rv` where `Rv` implements `Into<Value>`
 /// * `Result<Rv, Error>` where `Rv` implements `Into<Value>`
+/// * `Option<Rv>` where `Rv` implements `Into<Value>`.  `None` is
+///   converted into [`UNDEFINED`](Value::UNDEFINED).
+///
+/// To return types that only implement `Serialize` wrap them in
+/// [`Serializable`].
 ///
 /// The equivalent for test functions is [`TestResult`](crate::tests::TestResult).
 pub trait FunctionResult {
@@ -31,6 +36,12 @@ impl<I: Into<Value>> FunctionResult for Result<I, Error> {
     }
 }
 
+impl<I: Into<Value>> FunctionResult for Option<I> {
+    fn into_result(self) -> Result<Value, Error> {
+        Ok(self.map(Into::into).unwrap_or(Value::UNDEFINED))
+    }
+}
+
 impl<I: Into<Value>> FunctionResult for I {
     fn into_result(self) -> Result<Value, Error> {
         Ok(self.into())
@@ -625,7 +636,9 @@ impl<'a> ArgType<'a> for &[Value] {
 /// [`Test`](crate::tests::Test) or [`Function`](crate::functions::Function)
 /// this way.  The `Rest<T>` type will collect all the remaining arguments
 /// here.  It's implemented for all [`ArgType`]s.  The type itself deref's
-/// into the inner vector.
+/// into the inner vector.  Every argument is converted into `T` and the
+/// first argument that fails to convert produces an error that names its
+/// position.
 ///
 /// ```
 /// # use minijinja::Environment;
@@ -654,29 +667,75 @@ impl<T> DerefMut for Rest<T> {
     }
 }
 
-impl<'a, T: ArgType<'a, Output = T>> ArgType<'a> for Rest<T> {
-    type Output = Self;
+fn convert_rest<'a, T: ArgType<'a>>(
+    values: &'a [Value],
+    offset: usize,
+) -> Result<Rest<T::Output>, Error> {
+    values
+        .iter()
+        .enumerate()
+        .map(|(idx, v)| {
+            T::from_value(Some(v)).map_err(|mut err| {
+                let pos = offset + idx + 1;
+                let detail = match err.detail() {
+                    Some(detail) => format!("argument {pos}: {detail}"),
+                    None => format!("argument {pos}"),
+                };
+                err.set_detail(detail);
+                err
+            })
+        })
+        .collect::<Result<_, _>>()
+        .map(Rest)
+}
 
-    fn from_value(value: Option<&'a Value>) -> Result<Self, Error> {
-        Ok(Rest(ok!(value
-            .iter()
-            .map(|v| T::from_value(Some(v)))
-            .collect::<Result<_, _>>())))
+impl<'a, T: ArgType<'a>> ArgType<'a> for Rest<T> {
+    type Output = Rest<T::Output>;
+
+    fn from_value(value: Option<&'a Value>) -> Result<Self::Output, Error> {
+        convert_rest::<T>(value.map(std::slice::from_ref).unwrap_or_default(), 0)
     }
 
     fn from_state_and_values(
         _state: Option<&'a State>,
         values: &'a [Value],
         offset: usize,
-    ) -> Result<(Self, usize), Error> {
+    ) -> Result<(Self::Output, usize), Error> {
         let args = values.get(offset..).unwrap_or_default();
-        Ok((
-            Rest(ok!(args
-                .iter()
-                .map(|v| T::from_value(Some(v)))
-                .collect::<Result<_, _>>())),
-            args.len(),
-        ))
+        Ok((ok!(convert_rest::<T>(args, offset)), args.len()))
+    }
+}
+
+/// Utility type to return serializable values from functions.
+///
+/// Filters and functions can return any type that converts into a
+/// [`Value`].  For types that only implement [`Serialize`](serde::Serialize)
+/// this wrapper can be returned instead, which converts the inner value
+/// via [`Value::from_serializable`].
+///
+/// ```
+/// # use minijinja::Environment;
+/// # let mut env = Environment::new();
+/// use minijinja::value::Serializable;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// fn users(names: Vec<String>) -> Serializable<Vec<User>> {
+///     Serializable(names.into_iter().map(|name| User { name }).collect())
+/// }
+///
+/// env.add_filter("users", users);
+/// ```
+#[derive(Debug)]
+pub struct Serializable<T>(pub T);
+
+impl<T: serde::Serialize> From<Serializable<T>> for Value {
+    fn from(value: Serializable<T>) -> Self {
+        Value::from_serializable(&value.0)
     }
 }
 
@@ -856,6 +915,41 @@ impl Kwargs {
         }
         Ok(())
     }
+
+    /// Converts the keyword arguments that were not used into a map.
+    ///
+    /// This is the counterpart to [`assert_all_used`](Self::assert_all_used) for
+    /// functions that accept arbitrary keyword arguments.  All arguments that were
+    /// retrieved with [`get`](Self::get) are left out, the rest is returned as
+    /// a regular map value:
+    ///
+    /// ```
+    /// # use minijinja::Error;
+    /// # use minijinja::value::{Kwargs, Value};
+    /// fn link(url: &str, kwargs: Kwargs) -> Result<String, Error> {
+    ///     let title: Option<String> = kwargs.get("title")?;
+    ///     let attrs = kwargs.into_map();
+    ///     // attrs now holds all other arguments such as `class` or `id`
+    /// # let _ = (url, title, attrs);
+    /// # todo!()
+    /// }
+    /// ```
+    pub fn into_map(self) -> Value {
+        let used = self.used.into_inner();
+        let values = match Arc::try_unwrap(self.values) {
+            Ok(values) => values,
+            Err(values) => (*values).clone(),
+        };
+        Value(ValueRepr::Map(
+            Arc::new(
+                values
+                    .into_iter()
+                    .filter(|(k, _)| k.as_str().map_or(true, |k| !used.contains(k)))
+                    .collect(),
+            ),
+            MapType::Normal,
+        ))
+    }
 }
 
 impl FromIterator<(String, Value)> for Kwargs {
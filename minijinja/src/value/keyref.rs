@@ -141,7 +141,7 @@ pub mod key_interning {
             match set.get(s) {
                 Some(stored) => stored.clone(),
                 None => {
-                    let rv: Arc<str> = Arc::from(s.to_string());
+                    let rv: Arc<str> = Arc::from(s);
                     set.insert(rv.clone());
                     rv
                 }
@@ -83,6 +83,16 @@ let vec = Vec::<i32>::deserialize(value).unwrap();
 //! means they can be copied relatively cheaply.  Special care must be taken
 //! so that cycles are not created to avoid causing memory leaks.
 //!
+//! # Equality and Hashing
+//!
+//! Values implement [`Eq`] and [`Hash`] with the same semantics as the `==`
+//! operator in templates which means they can be used as keys in a
+//! [`HashMap`](std::collections::HashMap).  Numbers compare by their numeric
+//! value independent of their type so `1`, `1.0` and `true` are the same key.
+//! Sequences (including sequence objects) compare item by item and maps
+//! (including struct objects) compare by their entries independent of
+//! iteration order.  Other dynamic objects all hash to the same value.
+//!
 //! # HTML Escaping
 //!
 //! MiniJinja inherits the general desire to be clever about escaping.  For this
@@ -127,6 +137,7 @@ let vec = Vec::<i32>::deserialize(value).unwrap();
 
 use std::cell::{Cell, RefCell};
 use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::fmt;
@@ -139,12 +150,14 @@ use serde::ser::{Serialize, Serializer};
 use crate::error::{Error, ErrorKind};
 use crate::functions;
 use crate::utils::OnDrop;
-use crate::value::object::{SimpleSeqObject, SimpleStructObject};
+use crate::value::object::{IterableObject, SimpleSeqObject, SimpleStructObject};
 use crate::value::ops::as_f64;
 use crate::value::serialize::transform;
 use crate::vm::State;
 
-pub use crate::value::argtypes::{from_args, ArgType, FunctionArgs, FunctionResult, Kwargs, Rest};
+pub use crate::value::argtypes::{
+    from_args, ArgType, FunctionArgs, FunctionResult, Kwargs, Rest, Serializable,
+};
 pub use crate::value::object::{Object, ObjectKind, SeqObject, SeqObjectIter, StructObject};
 
 mod argtypes;
@@ -152,6 +165,7 @@ mod argtypes;
 mod deserialize;
 mod keyref;
 pub(crate) mod merge_object;
+pub(crate) mod namespace_object;
 mod object;
 pub(crate) mod ops;
 mod serialize;
@@ -352,32 +366,10 @@ impl Hash for Value {
         match &self.0 {
             ValueRepr::None | ValueRepr::Undefined => 0u8.hash(state),
             ValueRepr::String(ref s, _) => s.hash(state),
-            ValueRepr::Bool(b) => b.hash(state),
             ValueRepr::Invalid(s) => s.hash(state),
             ValueRepr::Bytes(b) => b.hash(state),
-            ValueRepr::Seq(b) => b.hash(state),
-            ValueRepr::Map(m, _) => m.iter().for_each(|(k, v)| {
-                k.hash(state);
-                v.hash(state);
-            }),
-            ValueRepr::Dynamic(d) => match d.kind() {
-                ObjectKind::Plain => 0u8.hash(state),
-                ObjectKind::Seq(s) => s.iter().for_each(|x| x.hash(state)),
-                ObjectKind::Struct(s) => {
-                    if let Some(fields) = s.static_fields() {
-                        fields.iter().for_each(|k| {
-                            k.hash(state);
-                            s.get_field(k).hash(state);
-                        });
-                    } else {
-                        s.fields().iter().for_each(|k| {
-                            k.hash(state);
-                            s.get_field(k).hash(state);
-                        });
-                    }
-                }
-            },
-            ValueRepr::U64(_)
+            ValueRepr::Bool(_)
+            | ValueRepr::U64(_)
             | ValueRepr::I64(_)
             | ValueRepr::F64(_)
             | ValueRepr::U128(_)
@@ -388,6 +380,30 @@ impl Hash for Value {
                     as_f64(self).map(|x| x.to_bits()).hash(state)
                 }
             }
+            ValueRepr::Seq(_) | ValueRepr::Map(..) | ValueRepr::Dynamic(_) => {
+                if let Some(seq) = self.as_seq() {
+                    seq.item_count().hash(state);
+                    seq.iter().for_each(|x| x.hash(state));
+                } else if self.kind() == ValueKind::Map {
+                    // maps compare equal independent of their iteration order,
+                    // so the entries are combined in an order independent way.
+                    let mut combined = 0u64;
+                    let mut count = 0usize;
+                    if let Ok(iter) = self.try_iter() {
+                        for key in iter {
+                            let mut entry_state = DefaultHasher::new();
+                            key.hash(&mut entry_state);
+                            self.get_item_opt(&key).hash(&mut entry_state);
+                            combined = combined.wrapping_add(entry_state.finish());
+                            count += 1;
+                        }
+                    }
+                    count.hash(state);
+                    combined.hash(state);
+                } else {
+                    0u8.hash(state);
+                }
+            }
         }
     }
 }
@@ -553,7 +569,7 @@ pub fn intern(s: &str) -> Arc<str> {
     }
     #[cfg(not(feature = "key_interning"))]
     {
-        Arc::from(s.to_string())
+        Arc::from(s)
     }
 }
 
@@ -599,6 +615,61 @@ impl Value {
         transform(value)
     }
 
+    /// Creates a value by parsing a JSON string.
+    ///
+    /// ```
+    /// # use minijinja::value::Value;
+    /// let val = Value::from_json_str(r#"{"items": [1, 2, 3]}"#).unwrap();
+    /// assert_eq!(val.get_attr("items").unwrap().len(), Some(3));
+    /// ```
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn from_json_str(s: &str) -> Result<Value, Error> {
+        serde_json::from_str::<serde_json::Value>(s)
+            .map(|value| Value::from_serializable(&value))
+            .map_err(|err| {
+                Error::new(ErrorKind::BadSerialization, "cannot parse JSON").with_source(err)
+            })
+    }
+
+    /// Serializes the value into a JSON string.
+    ///
+    /// Dynamic objects are serialized the same way as they would be when
+    /// passed to the `tojson` filter.  Unlike the filter, no escaping is
+    /// performed on the resulting string.
+    ///
+    /// ```
+    /// # use minijinja::value::Value;
+    /// let val = Value::from(vec![1, 2, 3]);
+    /// assert_eq!(val.to_json_string().unwrap(), "[1,2,3]");
+    /// ```
+    ///
+    /// Non-finite floats (`NaN` and infinity) cannot be represented in JSON
+    /// and result in an error.  To serialize them as `null` instead use
+    /// [`to_json_string_with_policy`](Self::to_json_string_with_policy).
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn to_json_string(&self) -> Result<String, Error> {
+        self.to_json_string_with_policy(crate::JsonFloatPolicy::default())
+    }
+
+    /// Serializes the value to a JSON string with a policy for non-finite floats.
+    ///
+    /// ```
+    /// # use minijinja::{value::Value, JsonFloatPolicy};
+    /// let val = Value::from(vec![1.0, f64::NAN]);
+    /// assert!(val.to_json_string_with_policy(JsonFloatPolicy::Error).is_err());
+    /// assert_eq!(val.to_json_string_with_policy(JsonFloatPolicy::Null).unwrap(), "[1.0,null]");
+    /// ```
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn to_json_string_with_policy(
+        &self,
+        policy: crate::JsonFloatPolicy,
+    ) -> Result<String, Error> {
+        policy.to_json_string(self, false)
+    }
+
     /// Creates a value from a safe string.
     ///
     /// A safe string is one that will bypass auto escaping.  For instance if you
@@ -613,6 +684,43 @@ impl Value {
         ValueRepr::String(Arc::from(value), StringType::Safe).into()
     }
 
+    /// Marks the value as safe.
+    ///
+    /// If the value is already a string, the string is reused without
+    /// reallocating.  Any other value is converted into a string first,
+    /// the same way the `safe` filter does.
+    ///
+    /// ```
+    /// # use minijinja::value::Value;
+    /// let val = Value::from("<em>note</em>").mark_safe();
+    /// assert!(val.is_safe());
+    /// ```
+    pub fn mark_safe(self) -> Value {
+        match self.0 {
+            ValueRepr::String(s, _) => ValueRepr::String(s, StringType::Safe).into(),
+            _ => Value::from_safe_string(self.to_string()),
+        }
+    }
+
+    /// Removes the safe marker from a string value.
+    ///
+    /// This is the inverse of [`mark_safe`](Self::mark_safe).  Values that are
+    /// not safe strings are returned unchanged.
+    ///
+    /// ```
+    /// # use minijinja::value::Value;
+    /// let val = Value::from_safe_string("<em>note</em>".into()).into_unsafe();
+    /// assert!(!val.is_safe());
+    /// ```
+    pub fn into_unsafe(self) -> Value {
+        match self.0 {
+            ValueRepr::String(s, StringType::Safe) => {
+                ValueRepr::String(s, StringType::Normal).into()
+            }
+            _ => self,
+        }
+    }
+
     /// Creates a value from a dynamic object.
     ///
     /// For more information see [`Object`].
@@ -665,10 +773,72 @@ impl Value {
         Value::from_object(SimpleSeqObject(value))
     }
 
+    /// Creates a sequence value that is produced by an iterator on demand.
+    ///
+    /// The `maker` function is invoked every time the value is iterated over
+    /// and has to return a fresh iterator whose items are then streamed.
+    /// This makes it possible to expose lazily computed sequences (for
+    /// instance backed by data held in an [`Arc`]) that can be iterated over
+    /// more than once.  As the length is not known up front, `loop.length`
+    /// and related attributes are undefined when looping over such a value.
+    ///
+    /// Indexing or calculating the length of the value invokes the maker as
+    /// well and walks the fresh iterator, so these operations are linear in
+    /// the number of items.
+    ///
+    /// ```
+    /// # use minijinja::value::Value;
+    /// let value = Value::make_iterable(|| (1..4).map(|x| Value::from(x * 2)));
+    /// let items = value.try_iter().unwrap().collect::<Vec<_>>();
+    /// assert_eq!(items, vec![Value::from(2), Value::from(4), Value::from(6)]);
+    /// ```
+    pub fn make_iterable<F, I>(maker: F) -> Value
+    where
+        F: Fn() -> I + Send + Sync + 'static,
+        I: Iterator<Item = Value> + Send + Sync + 'static,
+    {
+        Value::from_object(IterableObject::new(Box::new(move || {
+            Box::new(maker()) as Box<dyn Iterator<Item = Value> + Send + Sync>
+        })))
+    }
+
     /// Creates a value from an owned [`StructObject`].
     ///
     /// This is a simplified API for creating dynamic structs
-    /// without having to implement the entire [`Object`] protocol.
+    /// without having to implement the entire [`Object`] protocol.  The
+    /// resulting value behaves like a map in templates: fields can be
+    /// accessed as attributes or items, iterating over it yields the field
+    /// names and calling a method invokes the callable value stored in the
+    /// field of the same name.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, value::{StructObject, Value}};
+    /// struct Config {
+    ///     debug: bool,
+    /// }
+    ///
+    /// impl StructObject for Config {
+    ///     fn get_field(&self, name: &str) -> Option<Value> {
+    ///         match name {
+    ///             "debug" => Some(Value::from(self.debug)),
+    ///             "mode" => Some(Value::from_function(|| "strict")),
+    ///             _ => None,
+    ///         }
+    ///     }
+    ///
+    ///     fn static_fields(&self) -> Option<&'static [&'static str]> {
+    ///         Some(&["debug", "mode"][..])
+    ///     }
+    /// }
+    ///
+    /// let config = Value::from_struct_object(Config { debug: true });
+    /// let env = Environment::new();
+    /// let rv = env.render_str(
+    ///     "{{ config.debug }} {{ config.mode() }} {% for key in config %}[{{ key }}]{% endfor %}",
+    ///     minijinja::context! { config },
+    /// ).unwrap();
+    /// assert_eq!(rv, "true strict [debug][mode]");
+    /// ```
     pub fn from_struct_object<T: StructObject + 'static>(value: T) -> Value {
         Value::from_object(SimpleStructObject(value))
     }
@@ -717,6 +887,22 @@ impl Value {
         }
     }
 
+    /// Returns a compact debug representation of the value.
+    ///
+    /// Unlike the [`Display`](std::fmt::Display) implementation which is used
+    /// when a value is printed in a template, this quotes strings and renders
+    /// sequences and maps with their contents.  It's the single line form of
+    /// what the `pprint` filter produces.
+    ///
+    /// ```
+    /// # use minijinja::value::Value;
+    /// assert_eq!(Value::from("foo").to_repr(), r#""foo""#);
+    /// assert_eq!(Value::from(vec![1, 2]).to_repr(), "[1, 2]");
+    /// ```
+    pub fn to_repr(&self) -> String {
+        format!("{:?}", self)
+    }
+
     /// Returns `true` if the value is a number.
     ///
     /// To convert a value into a primitive number, use [`TryFrom`] or [`TryInto`].
@@ -773,6 +959,40 @@ impl Value {
         matches!(&self.0, ValueRepr::None)
     }
 
+    /// Compares two values the way the engine does.
+    ///
+    /// This applies the same ordering that is used by the comparison
+    /// operators and the `sort` filter, which includes cross-type comparisons
+    /// of numbers (eg: `1 < 1.5`).  Unlike the [`Ord`] implementation which
+    /// totally orders all values, this returns `None` if the two values are
+    /// not meaningfully comparable (eg: a string and a number).  Objects can
+    /// customize their ordering with [`Object::custom_cmp`] which is only
+    /// consulted here and by the engine, never by the [`Ord`] implementation.
+    ///
+    /// ```
+    /// # use std::cmp::Ordering;
+    /// # use minijinja::value::Value;
+    /// assert_eq!(Value::from(1).compare(&Value::from(1.5)), Some(Ordering::Less));
+    /// assert_eq!(Value::from("a").compare(&Value::from(1)), None);
+    /// ```
+    pub fn compare(&self, other: &Value) -> Option<Ordering> {
+        if let Some(rv) = ops::custom_cmp(self, other) {
+            Some(rv)
+        } else if self.kind() == other.kind() || ops::coerce(self, other).is_some() {
+            Some(Ord::cmp(self, other))
+        } else {
+            None
+        }
+    }
+
+    /// Checks two values for equality the way the `==` operator does.
+    ///
+    /// This is the same as the [`PartialEq`] implementation, but is provided
+    /// as method to make the intent explicit in host code.
+    pub fn eq_value(&self, other: &Value) -> bool {
+        self == other
+    }
+
     /// If the value is a string, return it.
     pub fn as_str(&self) -> Option<&str> {
         match &self.0 {
@@ -781,6 +1001,112 @@ impl Value {
         }
     }
 
+    /// If the value is a non-negative whole number, returns it as `u64`.
+    ///
+    /// Integers are accepted if they are in range and floats if they do not
+    /// have a fractional part.  Negative numbers, fractions and all other
+    /// values return `None`.
+    ///
+    /// ```
+    /// # use minijinja::value::Value;
+    /// assert_eq!(Value::from(42).as_u64(), Some(42));
+    /// assert_eq!(Value::from(2.0).as_u64(), Some(2));
+    /// assert_eq!(Value::from(-1).as_u64(), None);
+    /// assert_eq!(Value::from(1.5).as_u64(), None);
+    /// ```
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.0 {
+            ValueRepr::U64(v) => Some(v),
+            ValueRepr::I64(v) => u64::try_from(v).ok(),
+            ValueRepr::U128(v) => u64::try_from(v.0).ok(),
+            ValueRepr::I128(v) => u64::try_from(v.0).ok(),
+            // `u64::MAX as f64` rounds up to 2^64 which no longer fits
+            ValueRepr::F64(v) if v >= 0.0 && v.fract() == 0.0 && v < u64::MAX as f64 => {
+                Some(v as u64)
+            }
+            _ => None,
+        }
+    }
+
+    /// If the value is a non-negative whole number, returns it as `usize`.
+    ///
+    /// This works like [`as_u64`](Self::as_u64) and is useful for values that
+    /// are used as indexes.
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_u64().and_then(|v| usize::try_from(v).ok())
+    }
+
+    /// Converts the value into a string if it is one.
+    ///
+    /// Unlike [`to_string`](ToString::to_string) which stringifies any value,
+    /// this fails with an error for values that are not strings.  Safe
+    /// strings are returned as regular strings.
+    ///
+    /// ```
+    /// # use minijinja::value::Value;
+    /// assert_eq!(Value::from("foo").try_into_string().unwrap(), "foo");
+    /// assert!(Value::from(42).try_into_string().is_err());
+    /// ```
+    pub fn try_into_string(self) -> Result<String, Error> {
+        match self.0 {
+            ValueRepr::String(s, _) => Ok(s.to_string()),
+            _ => Err(Error::new(
+                ErrorKind::InvalidOperation,
+                format!("value of type {} is not a string", self.kind()),
+            )),
+        }
+    }
+
+    /// Creates a copy of the value that shares no state with the original.
+    ///
+    /// Cloning a [`Value`] is cheap because sequences, maps and objects are
+    /// reference counted, which also means that changes to a dynamic object
+    /// with interior mutability are visible through all clones.  This instead
+    /// recursively copies sequences and maps into new native containers.
+    /// Sequence and struct objects are materialized by enumerating them into
+    /// native sequences and maps.
+    ///
+    /// This severs the identity of objects: the copy no longer refers to the
+    /// original object, [`downcast_object_ref`](Self::downcast_object_ref)
+    /// will not find it, and methods or custom behavior implemented on the
+    /// object are not available on the copy.  Plain objects that cannot be
+    /// enumerated are retained as is.
+    ///
+    /// ```
+    /// # use minijinja::value::Value;
+    /// let value = Value::from_seq_object(vec![1, 2, 3]);
+    /// let copy = value.deep_clone();
+    /// assert_eq!(copy, Value::from(vec![1, 2, 3]));
+    /// assert!(copy.as_object().is_none());
+    /// ```
+    pub fn deep_clone(&self) -> Value {
+        match self.0 {
+            ValueRepr::Seq(ref items) => items.iter().map(Value::deep_clone).collect(),
+            ValueRepr::Map(ref items, map_type) => Value(ValueRepr::Map(
+                Arc::new(
+                    items
+                        .iter()
+                        .map(|(key, value)| (key.clone(), value.deep_clone()))
+                        .collect(),
+                ),
+                map_type,
+            )),
+            ValueRepr::Dynamic(ref dy) => match dy.kind() {
+                ObjectKind::Plain => self.clone(),
+                ObjectKind::Seq(_) | ObjectKind::Struct(_) => match self.as_pairs() {
+                    Some(pairs) => pairs
+                        .map(|(key, value)| (key, value.deep_clone()))
+                        .collect(),
+                    None => self
+                        .try_iter()
+                        .map(|iter| iter.map(|item| item.deep_clone()).collect())
+                        .unwrap_or_else(|_| self.clone()),
+                },
+            },
+            _ => self.clone(),
+        }
+    }
+
     /// Returns the bytes of this value if they exist.
     pub fn as_bytes(&self) -> Option<&[u8]> {
         match &self.0 {
@@ -847,9 +1173,11 @@ impl Value {
 
     /// Looks up an attribute by attribute name.
     ///
-    /// This this returns [`UNDEFINED`](Self::UNDEFINED) when an invalid key is
-    /// resolved.  An error is returned when if the value does not contain an object
-    /// that has attributes.
+    /// This resolves string keys on maps and fields on struct objects the same
+    /// way `value.name` does in templates, without having to create a [`Value`]
+    /// for the key.  This returns [`UNDEFINED`](Self::UNDEFINED) when an
+    /// invalid key is resolved.  An error is returned if the value does not
+    /// contain an object that has attributes.
     ///
     /// ```
     /// # use minijinja::value::Value;
@@ -864,12 +1192,7 @@ impl Value {
     pub fn get_attr(&self, key: &str) -> Result<Value, Error> {
         Ok(match self.0 {
             ValueRepr::Undefined => return Err(Error::from(ErrorKind::UndefinedError)),
-            ValueRepr::Map(ref items, _) => items.get(&KeyRef::Str(key)).cloned(),
-            ValueRepr::Dynamic(ref dy) => match dy.kind() {
-                ObjectKind::Struct(s) => s.get_field(key),
-                ObjectKind::Plain | ObjectKind::Seq(_) => None,
-            },
-            _ => None,
+            _ => self.get_attr_fast(key),
         }
         .unwrap_or(Value::UNDEFINED))
     }
@@ -886,14 +1209,18 @@ impl Value {
             ValueRepr::Dynamic(ref dy) => match dy.kind() {
                 ObjectKind::Struct(s) => s.get_field(key),
                 ObjectKind::Plain | ObjectKind::Seq(_) => None,
-            },
+            }
+            .or_else(|| dy.get_value_fallback(&Value::from(key))),
             _ => None,
         }
     }
 
     /// Looks up an index of the value.
     ///
-    /// This is a shortcut for [`get_item`](Self::get_item).
+    /// This is a shortcut for [`get_item`](Self::get_item).  For sequences
+    /// (including [`SeqObject`]s) the item is fetched directly without
+    /// going through an integer key.  Like [`get_item`](Self::get_item) this
+    /// returns [`UNDEFINED`](Self::UNDEFINED) for out of range indexes.
     ///
     /// ```
     /// # use minijinja::value::Value;
@@ -902,7 +1229,10 @@ impl Value {
     /// assert_eq!(value.try_into().ok(), Some(1));
     /// ```
     pub fn get_item_by_index(&self, idx: usize) -> Result<Value, Error> {
-        self.get_item(&Value(ValueRepr::U64(idx as _)))
+        match self.as_seq() {
+            Some(seq) => Ok(seq.get_item(idx).unwrap_or(Value::UNDEFINED)),
+            None => self.get_item(&Value(ValueRepr::U64(idx as _))),
+        }
     }
 
     /// Looks up an item (or attribute) by key.
@@ -928,15 +1258,99 @@ impl Value {
         }
     }
 
+    /// Checks if the value contains another value.
+    ///
+    /// This implements the same semantics as the `in` operator in templates:
+    /// strings are checked for substrings, sequences for membership and maps
+    /// for the presence of a key.  An undefined value never contains anything.
+    /// For all other values an error is returned.
+    ///
+    /// ```
+    /// # use minijinja::value::Value;
+    /// let seq = Value::from(vec![1, 2, 3]);
+    /// assert!(seq.contains(&Value::from(2)).unwrap());
+    /// assert!(!Value::from("foobar").contains(&Value::from("baz")).unwrap());
+    /// ```
+    pub fn contains(&self, needle: &Value) -> Result<bool, Error> {
+        ops::contains(self, needle).map(|rv| rv.is_true())
+    }
+
+    /// Adds two values like the `+` operator in templates.
+    ///
+    /// Numbers are promoted as necessary and strings are concatenated.
+    ///
+    /// ```
+    /// # use minijinja::value::Value;
+    /// let rv = Value::from(1).add(&Value::from(0.5)).unwrap();
+    /// assert_eq!(rv, Value::from(1.5));
+    /// ```
+    pub fn add(&self, other: &Value) -> Result<Value, Error> {
+        ops::add(self, other)
+    }
+
+    /// Subtracts a value like the `-` operator in templates.
+    pub fn sub(&self, other: &Value) -> Result<Value, Error> {
+        ops::sub(self, other)
+    }
+
+    /// Multiplies two values like the `*` operator in templates.
+    pub fn mul(&self, other: &Value) -> Result<Value, Error> {
+        ops::mul(self, other)
+    }
+
+    /// Divides two values like the `/` operator in templates.
+    ///
+    /// This always performs a float division.
+    pub fn div(&self, other: &Value) -> Result<Value, Error> {
+        ops::div(self, other)
+    }
+
+    /// Performs an integer division like the `//` operator in templates.
+    pub fn floor_div(&self, other: &Value) -> Result<Value, Error> {
+        ops::int_div(self, other)
+    }
+
+    /// Calculates the remainder like the `%` operator in templates.
+    pub fn rem(&self, other: &Value) -> Result<Value, Error> {
+        ops::rem(self, other)
+    }
+
+    /// Raises a value to a power like the `**` operator in templates.
+    pub fn pow(&self, other: &Value) -> Result<Value, Error> {
+        ops::pow(self, other)
+    }
+
+    /// Negates a number like the unary `-` operator in templates.
+    pub fn neg(&self) -> Result<Value, Error> {
+        ops::neg(self)
+    }
+
+    /// Concatenates the string forms of two values like the `~` operator
+    /// in templates.
+    ///
+    /// ```
+    /// # use minijinja::value::Value;
+    /// let rv = Value::from("answer: ").concat(&Value::from(42));
+    /// assert_eq!(rv.as_str(), Some("answer: 42"));
+    /// ```
+    pub fn concat(&self, other: &Value) -> Value {
+        ops::string_concat(self.clone(), other)
+    }
+
     /// Iterates over the value.
     ///
     /// Depending on the [`kind`](Self::kind) of the value the iterator
     /// has a different behavior.
     ///
-    /// * [`ValueKind::Map`]: the iterator yields the keys of the map.
+    /// * [`ValueKind::Map`]: the iterator yields the keys of the map.  This
+    ///   matches what happens when a map is iterated over in a template.
     /// * [`ValueKind::Seq`]: the iterator yields the items in the sequence.
+    /// * [`ValueKind::String`]: the iterator yields the characters of the string.
     /// * [`ValueKind::None`] / [`ValueKind::Undefined`]: the iterator is empty.
     ///
+    /// For all other values (eg: numbers or bools) an error is returned up
+    /// front.
+    ///
     /// ```
     /// # use minijinja::value::Value;
     /// # fn test() -> Result<(), minijinja::Error> {
@@ -959,6 +1373,36 @@ impl Value {
         })
     }
 
+    /// Iterates over the key and value pairs of a map.
+    ///
+    /// Returns `None` if the value is not a [`ValueKind::Map`].  The pairs are
+    /// yielded in the same order as [`try_iter`](Self::try_iter) yields the keys:
+    ///
+    /// * native maps iterate in key order, or in insertion order if the
+    ///   `preserve_order` feature is enabled.
+    /// * [`StructObject`]s iterate in the order of their
+    ///   [`fields`](StructObject::fields).
+    ///
+    /// ```
+    /// # use minijinja::value::Value;
+    /// let value = Value::from_iter([("a", 1), ("b", 2)]);
+    /// let pairs = value.as_pairs().unwrap().collect::<Vec<_>>();
+    /// assert_eq!(pairs, vec![
+    ///     (Value::from("a"), Value::from(1)),
+    ///     (Value::from("b"), Value::from(2)),
+    /// ]);
+    /// ```
+    pub fn as_pairs(&self) -> Option<impl Iterator<Item = (Value, Value)> + '_> {
+        if self.kind() != ValueKind::Map {
+            return None;
+        }
+        let keys = some!(self.try_iter().ok());
+        Some(keys.map(move |key| {
+            let value = self.get_item(&key).unwrap_or_default();
+            (key, value)
+        }))
+    }
+
     /// Returns some reference to the boxed object if it is of type `T`, or None if it isn’t.
     ///
     /// This is basically the "reverse" of [`from_object`](Self::from_object),
@@ -1016,7 +1460,26 @@ impl Value {
         self.as_object().and_then(|x| x.downcast_ref())
     }
 
+    /// Returns a copy of the value for a new scope.
+    ///
+    /// See [`Object::snapshot`] for details.
+    pub(crate) fn snapshot(&self) -> Value {
+        if let ValueRepr::Dynamic(ref dy) = self.0 {
+            if let Some(rv) = dy.snapshot() {
+                return Value(ValueRepr::Dynamic(rv));
+            }
+        }
+        self.clone()
+    }
+
     pub(crate) fn get_item_opt(&self, key: &Value) -> Option<Value> {
+        match (self.get_item_opt_direct(key), &self.0) {
+            (None, ValueRepr::Dynamic(dy)) => dy.get_value_fallback(key),
+            (rv, _) => rv,
+        }
+    }
+
+    fn get_item_opt_direct(&self, key: &Value) -> Option<Value> {
         let key = KeyRef::Value(key.clone());
 
         let seq = match self.0 {
@@ -1163,6 +1626,12 @@ impl Value {
                 items.len(),
             ),
             ValueRepr::Dynamic(ref obj) => {
+                if let Some(iterable) = obj.downcast_ref::<IterableObject>() {
+                    return Ok(OwnedValueIterator {
+                        iter_state: ValueIteratorState::Dyn(iterable.iter()),
+                        len: None,
+                    });
+                }
                 match obj.kind() {
                     ObjectKind::Plain => (ValueIteratorState::Empty, 0),
                     ObjectKind::Seq(s) => (
@@ -1190,7 +1659,10 @@ impl Value {
                 ))
             }
         };
-        Ok(OwnedValueIterator { iter_state, len })
+        Ok(OwnedValueIterator {
+            iter_state,
+            len: Some(len),
+        })
     }
 
     #[cfg(feature = "builtins")]
@@ -1252,6 +1724,15 @@ impl Serialize for Value {
                 }
                 map.end()
             }
+            ValueRepr::Dynamic(ref dy) if dy.is::<IterableObject>() => {
+                use serde::ser::SerializeSeq;
+                let iterable = dy.downcast_ref::<IterableObject>().unwrap();
+                let mut seq = ok!(serializer.serialize_seq(None));
+                for item in iterable.iter() {
+                    ok!(seq.serialize_element(&item));
+                }
+                seq.end()
+            }
             ValueRepr::Dynamic(ref dy) => match dy.kind() {
                 ObjectKind::Plain => serializer.serialize_str(&dy.to_string()),
                 ObjectKind::Seq(s) => {
@@ -1300,7 +1781,26 @@ impl<'a> Iterator for ValueIter<'a> {
 
 pub(crate) struct OwnedValueIterator {
     iter_state: ValueIteratorState,
-    len: usize,
+    len: Option<usize>,
+}
+
+impl OwnedValueIterator {
+    /// Returns the number of remaining items if known.
+    pub fn remaining(&self) -> Option<usize> {
+        self.len
+    }
+
+    /// Collects the remaining items if the length is not known.
+    pub fn buffered(self) -> OwnedValueIterator {
+        if self.len.is_some() {
+            return self;
+        }
+        let items = self.collect::<Vec<_>>();
+        OwnedValueIterator {
+            len: Some(items.len()),
+            iter_state: ValueIteratorState::Seq(0, Arc::new(items)),
+        }
+    }
 }
 
 impl Iterator for OwnedValueIterator {
@@ -1308,18 +1808,21 @@ impl Iterator for OwnedValueIterator {
 
     fn next(&mut self) -> Option<Self::Item> {
         self.iter_state.advance_state().map(|x| {
-            self.len -= 1;
+            if let Some(ref mut len) = self.len {
+                *len -= 1;
+            }
             x
         })
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.len, Some(self.len))
+        match self.len {
+            Some(len) => (len, Some(len)),
+            None => self.iter_state.size_hint(),
+        }
     }
 }
 
-impl ExactSizeIterator for OwnedValueIterator {}
-
 impl fmt::Debug for OwnedValueIterator {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ValueIterator").finish()
@@ -1333,6 +1836,7 @@ enum ValueIteratorState {
     StaticStr(usize, &'static [&'static str]),
     ArcStr(usize, Vec<Arc<str>>),
     DynSeq(usize, Arc<dyn Object>),
+    Dyn(Box<dyn Iterator<Item = Value> + Send + Sync>),
     #[cfg(not(feature = "preserve_order"))]
     Map(Option<KeyRef<'static>>, Arc<ValueMap>),
     #[cfg(feature = "preserve_order")]
@@ -1340,6 +1844,13 @@ enum ValueIteratorState {
 }
 
 impl ValueIteratorState {
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            ValueIteratorState::Dyn(iter) => iter.size_hint(),
+            _ => (0, None),
+        }
+    }
+
     fn advance_state(&mut self) -> Option<Value> {
         match self {
             ValueIteratorState::Empty => None,
@@ -1374,6 +1885,7 @@ impl ValueIteratorState {
                     unreachable!()
                 }
             }
+            ValueIteratorState::Dyn(iter) => iter.next(),
             #[cfg(feature = "preserve_order")]
             ValueIteratorState::Map(idx, map) => map.get_index(*idx).map(|x| {
                 *idx += 1;
@@ -1452,6 +1964,45 @@ mod tests {
         assert!(char::try_from(val).is_err());
     }
 
+    #[test]
+    #[cfg(feature = "key_interning")]
+    fn test_serialize_interns_map_keys() {
+        let rows = (0..1000)
+            .map(|idx| {
+                let mut row = BTreeMap::new();
+                row.insert("id".to_string(), idx.to_string());
+                row.insert("name".to_string(), format!("row {}", idx));
+                row
+            })
+            .collect::<Vec<_>>();
+        let value = Value::from_serializable(&rows);
+
+        let mut key_ptrs = BTreeMap::new();
+        for row in value.try_iter().unwrap() {
+            let map = match row.0 {
+                ValueRepr::Map(map, _) => map,
+                _ => panic!("expected map"),
+            };
+            for key in map.keys() {
+                let key = match key {
+                    KeyRef::Value(Value(ValueRepr::String(ref s, _))) => s.clone(),
+                    _ => panic!("expected string key"),
+                };
+                key_ptrs
+                    .entry(key.to_string())
+                    .or_insert_with(Vec::new)
+                    .push(Arc::as_ptr(&key));
+            }
+        }
+
+        // all maps share a single allocation per distinct key
+        assert_eq!(key_ptrs.len(), 2);
+        for ptrs in key_ptrs.values() {
+            assert_eq!(ptrs.len(), 1000);
+            assert!(ptrs.iter().all(|x| std::ptr::eq(*x, ptrs[0])));
+        }
+    }
+
     #[test]
     #[cfg(target_pointer_width = "64")]
     fn test_sizes() {
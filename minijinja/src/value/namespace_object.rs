@@ -0,0 +1,58 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use crate::value::object::{Object, ObjectKind, StructObject};
+use crate::value::Value;
+
+/// A mutable object as created by the `namespace()` function.
+///
+/// Unlike all other values the attributes of a namespace can be assigned
+/// to from templates with `{% set ns.attr = value %}`.  This makes it
+/// possible to carry values out of nested scopes such as loops.
+#[derive(Default)]
+pub struct Namespace(Mutex<BTreeMap<Arc<str>, Value>>);
+
+impl Namespace {
+    /// Assigns a value to an attribute.
+    pub fn set_field(&self, key: &str, value: Value) {
+        self.0.lock().unwrap().insert(Arc::from(key), value);
+    }
+}
+
+impl fmt::Debug for Namespace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.0.lock().unwrap().iter())
+            .finish()
+    }
+}
+
+impl fmt::Display for Namespace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        ok!(f.write_str("<namespace {"));
+        for (idx, (key, value)) in self.0.lock().unwrap().iter().enumerate() {
+            if idx > 0 {
+                ok!(f.write_str(", "));
+            }
+            ok!(write!(f, "{key:?}: {value:?}"));
+        }
+        f.write_str("}>")
+    }
+}
+
+impl Object for Namespace {
+    fn kind(&self) -> ObjectKind<'_> {
+        ObjectKind::Struct(self)
+    }
+}
+
+impl StructObject for Namespace {
+    fn get_field(&self, name: &str) -> Option<Value> {
+        self.0.lock().unwrap().get(name).cloned()
+    }
+
+    fn fields(&self) -> Vec<Arc<str>> {
+        self.0.lock().unwrap().keys().cloned().collect()
+    }
+}
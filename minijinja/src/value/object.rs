@@ -1,4 +1,5 @@
 use std::any::{Any, TypeId};
+use std::cmp::Ordering;
 use std::fmt;
 use std::ops::Range;
 use std::sync::Arc;
@@ -74,6 +75,84 @@ pub trait Object: fmt::Display + fmt::Debug + Any + Sync + Send {
             "tried to call non callable object",
         ))
     }
+
+    /// Compares the object to another value.
+    ///
+    /// This is consulted whenever the engine needs to order an object, for
+    /// instance in comparisons or when sorting.  If `None` is returned
+    /// (which is the default) the engine falls back to its regular ordering
+    /// of values.  The `other` value is not necessarily an object.
+    ///
+    /// The [`Ord`] implementation of [`Value`] does not consult this method
+    /// so that values stay usable as keys in ordered maps.  To compare values
+    /// from Rust with the custom ordering use [`Value::compare`].
+    ///
+    /// ```rust
+    /// # use std::cmp::Ordering;
+    /// # use std::fmt;
+    /// # use minijinja::value::{Value, Object};
+    /// #[derive(Debug)]
+    /// struct Version(u32, u32);
+    ///
+    /// # impl fmt::Display for Version {
+    /// #     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    /// #         write!(f, "{}.{}", self.0, self.1)
+    /// #     }
+    /// # }
+    /// impl Object for Version {
+    ///     fn custom_cmp(&self, other: &Value) -> Option<Ordering> {
+    ///         let other = other.downcast_object_ref::<Version>()?;
+    ///         Some((self.0, self.1).cmp(&(other.0, other.1)))
+    ///     }
+    /// }
+    /// ```
+    fn custom_cmp(&self, other: &Value) -> Option<Ordering> {
+        let _other = other;
+        None
+    }
+
+    /// Creates a copy of the object for a new scope.
+    ///
+    /// Objects are reference counted and by default the same object is
+    /// shared by all scopes that refer to it.  Objects with interior
+    /// mutability can return a copy of themselves here so that changes made
+    /// in a new scope do not leak back out of it.  Today this is invoked for
+    /// values assigned in a `{% with %}` block.  The default implementation
+    /// returns `None` which shares the object.
+    fn snapshot(&self) -> Option<Arc<dyn Object>> {
+        None
+    }
+
+    /// Looks up a key that could not be resolved otherwise.
+    ///
+    /// This is consulted by the engine for attribute and item lookups after
+    /// the regular lookup for the object's [kind](Self::kind) (for instance
+    /// [`StructObject::get_field`]) came up empty.  It lets objects resolve
+    /// keys dynamically, for instance by forwarding to a backing store.  The
+    /// key is usually a string for attribute lookups but can be any value
+    /// for subscript lookups.  The default implementation returns `None`.
+    ///
+    /// ```rust
+    /// # use std::fmt;
+    /// # use minijinja::value::{Value, Object};
+    /// #[derive(Debug)]
+    /// struct Env;
+    ///
+    /// # impl fmt::Display for Env {
+    /// #     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    /// #         f.write_str("env")
+    /// #     }
+    /// # }
+    /// impl Object for Env {
+    ///     fn get_value_fallback(&self, key: &Value) -> Option<Value> {
+    ///         std::env::var(key.as_str()?).ok().map(Value::from)
+    ///     }
+    /// }
+    /// ```
+    fn get_value_fallback(&self, key: &Value) -> Option<Value> {
+        let _key = key;
+        None
+    }
 }
 
 impl dyn Object {
@@ -177,6 +256,21 @@ impl<T: Object> Object for Arc<T> {
     fn call(&self, state: &State, args: &[Value]) -> Result<Value, Error> {
         T::call(self, state, args)
     }
+
+    #[inline]
+    fn custom_cmp(&self, other: &Value) -> Option<Ordering> {
+        T::custom_cmp(self, other)
+    }
+
+    #[inline]
+    fn snapshot(&self) -> Option<Arc<dyn Object>> {
+        T::snapshot(self)
+    }
+
+    #[inline]
+    fn get_value_fallback(&self, key: &Value) -> Option<Value> {
+        T::get_value_fallback(self, key)
+    }
 }
 
 /// A kind defines the object's behavior.
@@ -298,6 +392,11 @@ pub trait SeqObject: Send + Sync {
     fn get_item(&self, idx: usize) -> Option<Value>;
 
     /// Returns the number of items in the sequence.
+    ///
+    /// This is also used to resolve negative indexes in templates: `seq[-1]`
+    /// is looked up as `get_item(item_count() - 1)`.  Negative indexes that
+    /// go past the start of the sequence produce an undefined value, just like
+    /// indexes past the end.
     fn item_count(&self) -> usize;
 }
 
@@ -558,7 +657,10 @@ pub trait StructObject: Send + Sync {
     /// Returns the number of fields.
     ///
     /// The default implementation uses [`fields`](Self::fields) and
-    /// [`static_fields`](Self::static_fields) automatically.
+    /// [`static_fields`](Self::static_fields) automatically.  Objects with
+    /// dynamic fields that know their size without enumerating all fields
+    /// should override this as it's used by the `length` filter and to
+    /// determine the truthiness of the object.
     fn field_count(&self) -> usize {
         if let Some(fields) = self.static_fields() {
             fields.len()
@@ -674,4 +776,70 @@ impl<T: StructObject + 'static> Object for SimpleStructObject<T> {
     fn kind(&self) -> ObjectKind<'_> {
         ObjectKind::Struct(&self.0)
     }
+
+    fn call_method(&self, state: &State, name: &str, args: &[Value]) -> Result<Value, Error> {
+        // like with maps, methods are callable values stored in fields
+        match self.0.get_field(name) {
+            Some(value) => value.call(state, args),
+            None => Err(Error::new(
+                ErrorKind::UnknownMethod,
+                format!("object has no method named {name}"),
+            )),
+        }
+    }
+}
+
+type IterMakerFunc = dyn Fn() -> Box<dyn Iterator<Item = Value> + Send + Sync> + Send + Sync;
+
+/// A sequence that is produced by a fresh iterator every time.
+///
+/// Iterating calls the maker and streams its items.  Indexing and length
+/// calculations also call the maker and walk the fresh iterator, so they
+/// always agree with iteration but are linear in the number of items.
+pub struct IterableObject(Box<IterMakerFunc>);
+
+impl IterableObject {
+    pub fn new(maker: Box<IterMakerFunc>) -> IterableObject {
+        IterableObject(maker)
+    }
+
+    /// Returns a fresh iterator over the items.
+    pub fn iter(&self) -> Box<dyn Iterator<Item = Value> + Send + Sync> {
+        (self.0)()
+    }
+}
+
+impl fmt::Display for IterableObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        ok!(f.write_str("["));
+        for (idx, val) in self.iter().enumerate() {
+            if idx > 0 {
+                ok!(f.write_str(", "));
+            }
+            ok!(write!(f, "{val:?}"));
+        }
+        f.write_str("]")
+    }
+}
+
+impl fmt::Debug for IterableObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl SeqObject for IterableObject {
+    fn get_item(&self, idx: usize) -> Option<Value> {
+        self.iter().nth(idx)
+    }
+
+    fn item_count(&self) -> usize {
+        self.iter().count()
+    }
+}
+
+impl Object for IterableObject {
+    fn kind(&self) -> ObjectKind<'_> {
+        ObjectKind::Seq(self)
+    }
 }
@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::convert::{TryFrom, TryInto};
 
 use crate::error::{Error, ErrorKind};
@@ -47,6 +48,26 @@ pub fn coerce<'x>(a: &'x Value, b: &'x Value) -> Option<CoerceResult<'x>> {
     }
 }
 
+/// Consults the custom ordering of objects.
+pub fn custom_cmp(a: &Value, b: &Value) -> Option<Ordering> {
+    match (&a.0, &b.0) {
+        (ValueRepr::Dynamic(x), ValueRepr::Dynamic(y)) => x
+            .custom_cmp(b)
+            .or_else(|| y.custom_cmp(a).map(Ordering::reverse)),
+        (ValueRepr::Dynamic(x), _) => x.custom_cmp(b),
+        (_, ValueRepr::Dynamic(y)) => y.custom_cmp(a).map(Ordering::reverse),
+        _ => None,
+    }
+}
+
+/// Orders two values the way the engine does.
+///
+/// This is the structural ordering of [`Ord`] unless an object provides a
+/// custom ordering.
+pub fn cmp(a: &Value, b: &Value) -> Ordering {
+    custom_cmp(a, b).unwrap_or_else(|| a.cmp(b))
+}
+
 fn get_offset_and_len<F: FnOnce() -> usize>(
     start: i64,
     stop: Option<i64>,
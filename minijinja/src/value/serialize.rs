@@ -5,8 +5,8 @@ use serde::{ser, Serialize, Serializer};
 
 use crate::utils::untrusted_size_hint;
 use crate::value::{
-    value_map_with_capacity, Arc, KeyRef, MapType, Packed, StringType, Value, ValueMap, ValueRepr,
-    VALUE_HANDLES, VALUE_HANDLE_MARKER,
+    intern, value_map_with_capacity, Arc, KeyRef, MapType, Packed, StringType, Value, ValueMap,
+    ValueRepr, VALUE_HANDLES, VALUE_HANDLE_MARKER,
 };
 
 #[derive(Debug)]
@@ -107,11 +107,11 @@ impl Serializer for ValueSerializer {
     }
 
     fn serialize_char(self, v: char) -> Result<Value, InvalidValue> {
-        Ok(ValueRepr::String(Arc::from(v.to_string()), StringType::Normal).into())
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
     }
 
     fn serialize_str(self, value: &str) -> Result<Value, InvalidValue> {
-        Ok(ValueRepr::String(Arc::from(value.to_owned()), StringType::Normal).into())
+        Ok(ValueRepr::String(Arc::from(value), StringType::Normal).into())
     }
 
     fn serialize_bytes(self, value: &[u8]) -> Result<Value, InvalidValue> {
@@ -247,6 +247,94 @@ impl Serializer for ValueSerializer {
     }
 }
 
+/// Serializer for map keys.
+///
+/// Map keys tend to repeat a lot (think of a long list of maps with the same
+/// keys) so string keys are [interned](crate::value::intern).  Everything
+/// else is forwarded to the [`ValueSerializer`].
+struct KeySerializer;
+
+macro_rules! forward_to_value_serializer {
+    ($($name:ident($($arg:ident: $ty:ty),*) -> $rv:ty;)*) => {
+        $(
+            fn $name(self, $($arg: $ty),*) -> Result<$rv, InvalidValue> {
+                ValueSerializer.$name($($arg),*)
+            }
+        )*
+    };
+}
+
+impl Serializer for KeySerializer {
+    type Ok = Value;
+    type Error = InvalidValue;
+
+    type SerializeSeq = SerializeSeq;
+    type SerializeTuple = SerializeTuple;
+    type SerializeTupleStruct = SerializeTupleStruct;
+    type SerializeTupleVariant = SerializeTupleVariant;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeStruct;
+    type SerializeStructVariant = SerializeStructVariant;
+
+    fn serialize_str(self, value: &str) -> Result<Value, InvalidValue> {
+        Ok(ValueRepr::String(intern(value), StringType::Normal).into())
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, InvalidValue> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Value, InvalidValue> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, InvalidValue> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, InvalidValue> {
+        ValueSerializer.serialize_newtype_variant(name, variant_index, variant, value)
+    }
+
+    forward_to_value_serializer! {
+        serialize_bool(v: bool) -> Value;
+        serialize_i8(v: i8) -> Value;
+        serialize_i16(v: i16) -> Value;
+        serialize_i32(v: i32) -> Value;
+        serialize_i64(v: i64) -> Value;
+        serialize_i128(v: i128) -> Value;
+        serialize_u8(v: u8) -> Value;
+        serialize_u16(v: u16) -> Value;
+        serialize_u32(v: u32) -> Value;
+        serialize_u64(v: u64) -> Value;
+        serialize_u128(v: u128) -> Value;
+        serialize_f32(v: f32) -> Value;
+        serialize_f64(v: f64) -> Value;
+        serialize_bytes(v: &[u8]) -> Value;
+        serialize_none() -> Value;
+        serialize_unit() -> Value;
+        serialize_unit_struct(name: &'static str) -> Value;
+        serialize_unit_variant(name: &'static str, variant_index: u32, variant: &'static str) -> Value;
+        serialize_seq(len: Option<usize>) -> SerializeSeq;
+        serialize_tuple(len: usize) -> SerializeTuple;
+        serialize_tuple_struct(name: &'static str, len: usize) -> SerializeTupleStruct;
+        serialize_tuple_variant(name: &'static str, variant_index: u32, variant: &'static str, len: usize) -> SerializeTupleVariant;
+        serialize_map(len: Option<usize>) -> SerializeMap;
+        serialize_struct(name: &'static str, len: usize) -> SerializeStruct;
+        serialize_struct_variant(name: &'static str, variant_index: u32, variant: &'static str, len: usize) -> SerializeStructVariant;
+    }
+}
+
 pub struct SerializeSeq {
     elements: Vec<Value>,
 }
@@ -350,7 +438,7 @@ impl ser::SerializeMap for SerializeMap {
     where
         T: Serialize,
     {
-        match key.serialize(ValueSerializer) {
+        match key.serialize(KeySerializer) {
             Ok(key) => self.key = Some(key),
             Err(_) => self.key = None,
         }
@@ -383,7 +471,7 @@ impl ser::SerializeMap for SerializeMap {
         K: Serialize,
         V: Serialize,
     {
-        if let Ok(key) = key.serialize(ValueSerializer) {
+        if let Ok(key) = key.serialize(KeySerializer) {
             self.entries.insert(KeyRef::Value(key), transform(value));
         }
         Ok(())
@@ -7,7 +7,7 @@ use crate::value::{Object, ObjectKind, StructObject, Value};
 use crate::vm::state::State;
 
 pub(crate) struct Loop {
-    pub len: usize,
+    pub len: Option<usize>,
     pub idx: AtomicUsize,
     pub depth: usize,
     #[cfg(feature = "adjacent_loop_items")]
@@ -49,11 +49,11 @@ impl Object for Loop {
                 Ok(Value::from(false))
             }
         } else if name == "cycle" {
-            let idx = self.idx.load(Ordering::Relaxed);
-            match args.get(idx % args.len()) {
-                Some(arg) => Ok(arg.clone()),
-                None => Ok(Value::UNDEFINED),
+            if args.is_empty() {
+                return Ok(Value::UNDEFINED);
             }
+            let idx = self.idx.load(Ordering::Relaxed);
+            Ok(args[idx % args.len()].clone())
         } else {
             Err(Error::new(
                 ErrorKind::UnknownMethod,
@@ -92,15 +92,23 @@ impl StructObject for Loop {
         if idx == !0 {
             return Some(Value::UNDEFINED);
         }
-        let len = self.len as u64;
+        // the length is unknown for iterators that are streamed, in which
+        // case all attributes derived from it are undefined.
+        let len = self.len.map(|x| x as u64);
         match name {
             "index0" => Some(Value::from(idx)),
             "index" => Some(Value::from(idx + 1)),
-            "length" => Some(Value::from(len)),
-            "revindex" => Some(Value::from(len.saturating_sub(idx))),
-            "revindex0" => Some(Value::from(len.saturating_sub(idx).saturating_sub(1))),
+            "length" => Some(len.map_or(Value::UNDEFINED, Value::from)),
+            "revindex" => {
+                Some(len.map_or(Value::UNDEFINED, |len| Value::from(len.saturating_sub(idx))))
+            }
+            "revindex0" => Some(len.map_or(Value::UNDEFINED, |len| {
+                Value::from(len.saturating_sub(idx).saturating_sub(1))
+            })),
             "first" => Some(Value::from(idx == 0)),
-            "last" => Some(Value::from(len == 0 || idx == len - 1)),
+            "last" => Some(len.map_or(Value::UNDEFINED, |len| {
+                Value::from(len == 0 || idx == len - 1)
+            })),
             "depth" => Some(Value::from(self.depth + 1)),
             "depth0" => Some(Value::from(self.depth)),
             #[cfg(feature = "adjacent_loop_items")]
@@ -128,11 +136,10 @@ impl StructObject for Loop {
 
 impl fmt::Display for Loop {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "<loop {}/{}>",
-            self.idx.load(Ordering::Relaxed),
-            self.len
-        )
+        let idx = self.idx.load(Ordering::Relaxed);
+        match self.len {
+            Some(len) => write!(f, "<loop {idx}/{len}>"),
+            None => write!(f, "<loop {idx}/?>"),
+        }
     }
 }
@@ -10,6 +10,7 @@ use crate::environment::Environment;
 use crate::error::{Error, ErrorKind};
 use crate::output::{CaptureMode, Output};
 use crate::utils::{untrusted_size_hint, AutoEscape, UndefinedBehavior};
+use crate::value::namespace_object::Namespace;
 use crate::value::{
     ops, value_map_with_capacity, value_optimization, KeyRef, MapType, Value, ValueRepr,
 };
@@ -128,6 +129,9 @@ impl<'env> Vm<'env> {
                 instructions,
                 blocks: BTreeMap::default(),
                 loaded_templates: Default::default(),
+                #[cfg(feature = "multi_template")]
+                include_depth: state.include_depth,
+                temps: state.temps.clone(),
                 #[cfg(feature = "macros")]
                 id: state.id,
                 #[cfg(feature = "macros")]
@@ -258,6 +262,14 @@ impl<'env> Vm<'env> {
                 }};
             }
 
+            macro_rules! op_cmp {
+                ($check:ident) => {{
+                    b = stack.pop();
+                    a = stack.pop();
+                    stack.push(Value::from(ops::cmp(&a, &b).$check()));
+                }};
+            }
+
             macro_rules! bail {
                 ($err:expr) => {{
                     err = $err;
@@ -304,10 +316,24 @@ impl<'env> Vm<'env> {
                 Instruction::StoreLocal(name) => {
                     state.ctx.store(name, stack.pop());
                 }
+                Instruction::SetAttr(name) => {
+                    b = stack.pop();
+                    a = stack.pop();
+                    if let Some(ns) = b.downcast_object_ref::<Namespace>() {
+                        ns.set_field(name, a);
+                    } else {
+                        bail!(Error::new(
+                            ErrorKind::InvalidOperation,
+                            format!("can only assign to namespaces, not {}", b.kind())
+                        ));
+                    }
+                }
                 Instruction::Lookup(name) => {
-                    stack.push(assert_valid!(state
-                        .lookup(name)
-                        .unwrap_or(Value::UNDEFINED)));
+                    let value = state.lookup(name).unwrap_or(Value::UNDEFINED);
+                    if value.is_undefined() {
+                        self.notify_undefined_access(state, name, pc);
+                    }
+                    stack.push(assert_valid!(value));
                 }
                 Instruction::GetAttr(name) => {
                     a = stack.pop();
@@ -318,7 +344,12 @@ impl<'env> Vm<'env> {
                     // special case.
                     stack.push(match a.get_attr_fast(name) {
                         Some(value) => assert_valid!(value),
-                        None => ctx_ok!(undefined_behavior.handle_undefined(a.is_undefined())),
+                        None => {
+                            if !a.is_undefined() {
+                                self.notify_undefined_access(state, name, pc);
+                            }
+                            ctx_ok!(undefined_behavior.handle_undefined(a.is_undefined()))
+                        }
                     });
                 }
                 Instruction::GetItem => {
@@ -393,10 +424,10 @@ impl<'env> Vm<'env> {
                 Instruction::Pow => func_binop!(pow),
                 Instruction::Eq => op_binop!(==),
                 Instruction::Ne => op_binop!(!=),
-                Instruction::Gt => op_binop!(>),
-                Instruction::Gte => op_binop!(>=),
-                Instruction::Lt => op_binop!(<),
-                Instruction::Lte => op_binop!(<=),
+                Instruction::Gt => op_cmp!(is_gt),
+                Instruction::Gte => op_cmp!(is_ge),
+                Instruction::Lt => op_cmp!(is_lt),
+                Instruction::Lte => op_cmp!(is_le),
                 Instruction::Not => {
                     a = stack.pop();
                     stack.push(Value::from(!a.is_true()));
@@ -421,6 +452,10 @@ impl<'env> Vm<'env> {
                 Instruction::PushWith => {
                     ctx_ok!(state.ctx.push_frame(Frame::default()));
                 }
+                Instruction::Snapshot => {
+                    a = stack.pop();
+                    stack.push(a.snapshot());
+                }
                 Instruction::PopFrame => {
                     if let Some(mut loop_ctx) = state.ctx.pop_frame().current_loop {
                         if let Some((target, end_capture)) = loop_ctx.current_recursion_jump.take()
@@ -520,21 +555,29 @@ impl<'env> Vm<'env> {
                     stack.push(out.end_capture(state.auto_escape));
                 }
                 Instruction::ApplyFilter(name, arg_count, local_id) => {
-                    let filter =
-                        ctx_ok!(get_or_lookup_local(&mut loaded_filters, *local_id, || {
-                            state.env.get_filter(name)
-                        })
-                        .ok_or_else(|| {
-                            Error::new(
-                                ErrorKind::UnknownFilter,
-                                format!("filter {name} is unknown"),
-                            )
-                        }));
                     let args = stack.slice_top(*arg_count);
-                    a = ctx_ok!(filter.apply_to(state, args));
+                    a = match get_or_lookup_local(&mut loaded_filters, *local_id, || {
+                        state.env.get_filter(name)
+                    }) {
+                        Some(filter) => ctx_ok!(filter.apply_to(state, args)),
+                        None => ctx_ok!(self.env.apply_unknown_filter(name, state, args)),
+                    };
                     stack.drop_top(*arg_count);
                     stack.push(a);
                 }
+                Instruction::EmitFilter(name, arg_count, local_id) => {
+                    let args = stack.slice_top(*arg_count);
+                    match get_or_lookup_local(&mut loaded_filters, *local_id, || {
+                        state.env.get_filter(name)
+                    }) {
+                        Some(filter) => ctx_ok!(filter.emit_to(state, args, out)),
+                        None => {
+                            a = ctx_ok!(self.env.apply_unknown_filter(name, state, args));
+                            ctx_ok!(self.env.format(&a, state, out));
+                        }
+                    }
+                    stack.drop_top(*arg_count);
+                }
                 Instruction::PerformTest(name, arg_count, local_id) => {
                     let test = ctx_ok!(get_or_lookup_local(&mut loaded_tests, *local_id, || {
                         state.env.get_test(name)
@@ -721,7 +764,10 @@ impl<'env> Vm<'env> {
                 }
             };
 
+            ok!(self.check_include_depth(state, name));
             let (new_instructions, new_blocks) = ok!(tmpl.instructions_and_blocks());
+            let old_include_depth = state.include_depth;
+            state.include_depth += 1;
             let old_escape = mem::replace(&mut state.auto_escape, tmpl.initial_auto_escape());
             let old_instructions = mem::replace(&mut state.instructions, new_instructions);
             let old_blocks = mem::replace(&mut state.blocks, prepare_blocks(new_blocks));
@@ -738,6 +784,7 @@ impl<'env> Vm<'env> {
                 rv = self.eval_state(state, out);
             }
             state.ctx.decr_depth(INCLUDE_RECURSION_COST);
+            state.include_depth = old_include_depth;
             state.auto_escape = old_escape;
             state.instructions = old_instructions;
             state.blocks = old_blocks;
@@ -848,9 +895,11 @@ impl<'env> Vm<'env> {
                 format!("cycle in template inheritance. {name:?} was referenced more than once"),
             ));
         }
+        ok!(self.check_include_depth(state, name));
         let tmpl = ok!(state.get_template(name));
         let (new_instructions, new_blocks) = ok!(tmpl.instructions_and_blocks());
         state.loaded_templates.insert(new_instructions.name());
+        state.include_depth += 1;
         for (name, instr) in new_blocks.iter() {
             state
                 .blocks
@@ -861,6 +910,17 @@ impl<'env> Vm<'env> {
         Ok(new_instructions)
     }
 
+    #[cfg(feature = "multi_template")]
+    fn check_include_depth(&self, state: &State, name: &str) -> Result<(), Error> {
+        match self.env.max_include_depth() {
+            Some(max_depth) if state.include_depth >= max_depth => Err(Error::new(
+                ErrorKind::InvalidOperation,
+                format!("maximum include depth ({max_depth}) exceeded when loading {name:?}"),
+            )),
+            _ => Ok(()),
+        }
+    }
+
     #[cfg(feature = "multi_template")]
     pub(crate) fn call_block(
         &self,
@@ -916,9 +976,12 @@ impl<'env> Vm<'env> {
         pc: usize,
         current_recursion_jump: Option<(usize, bool)>,
     ) -> Result<(), Error> {
-        #[allow(unused_mut)]
+        let iterable = state.env().map_key_order().apply(iterable);
         let mut iterator = ok!(state.undefined_behavior().try_iter(iterable));
-        let len = iterator.len();
+        if state.env().loop_length_buffering() {
+            iterator = iterator.buffered();
+        }
+        let len = iterator.remaining();
         let depth = state
             .ctx
             .current_loop()
@@ -946,6 +1009,13 @@ impl<'env> Vm<'env> {
         Ok(())
     }
 
+    #[inline(always)]
+    fn notify_undefined_access(&self, state: &State, name: &str, pc: usize) {
+        if let Some(callback) = self.env.undefined_access_callback() {
+            callback(state, name, state.instructions.get_line(pc));
+        }
+    }
+
     fn unpack_list(&self, stack: &mut Stack, count: &usize) -> Result<(), Error> {
         let top = stack.pop();
         let seq = ok!(top
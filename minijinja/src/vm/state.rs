@@ -1,5 +1,6 @@
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
+use std::sync::{Arc, Mutex};
 
 use crate::compiler::instructions::Instructions;
 use crate::environment::Environment;
@@ -47,6 +48,9 @@ pub struct State<'template, 'env> {
     pub(crate) blocks: BTreeMap<&'env str, BlockStack<'template, 'env>>,
     #[allow(unused)]
     pub(crate) loaded_templates: BTreeSet<&'env str>,
+    #[cfg(feature = "multi_template")]
+    pub(crate) include_depth: usize,
+    pub(crate) temps: Arc<Mutex<BTreeMap<Box<str>, Value>>>,
     #[cfg(feature = "macros")]
     pub(crate) id: isize,
     #[cfg(feature = "macros")]
@@ -88,6 +92,9 @@ impl<'template, 'env> State<'template, 'env> {
             instructions,
             blocks,
             loaded_templates: BTreeSet::new(),
+            #[cfg(feature = "multi_template")]
+            include_depth: 0,
+            temps: Default::default(),
             #[cfg(feature = "macros")]
             macros: Default::default(),
             #[cfg(feature = "macros")]
@@ -109,6 +116,9 @@ impl<'template, 'env> State<'template, 'env> {
     }
 
     /// Returns a reference to the current environment.
+    ///
+    /// This can be used by filters, functions and objects to look up
+    /// configuration or to render other templates of the environment.
     #[inline(always)]
     pub fn env(&self) -> &Environment<'_> {
         self.env
@@ -120,6 +130,22 @@ impl<'template, 'env> State<'template, 'env> {
     }
 
     /// Returns the current value of the auto escape flag.
+    ///
+    /// This reflects the auto escaping of the current template as well as
+    /// `{% autoescape %}` blocks.  Filters that produce markup can use this
+    /// to decide if they need to escape or mark their output as safe.
+    ///
+    /// ```
+    /// # use minijinja::{AutoEscape, Environment, State, value::Value};
+    /// fn bold(state: &State, value: String) -> Value {
+    ///     match state.auto_escape() {
+    ///         AutoEscape::Html => Value::from_safe_string(format!("<b>{value}</b>")),
+    ///         _ => Value::from(format!("**{value}**")),
+    ///     }
+    /// }
+    /// # let mut env = Environment::new();
+    /// # env.add_filter("bold", bold);
+    /// ```
     #[inline(always)]
     pub fn auto_escape(&self) -> AutoEscape {
         self.auto_escape
@@ -239,7 +265,7 @@ impl<'template, 'env> State<'template, 'env> {
     pub fn apply_filter(&self, filter: &str, args: &[Value]) -> Result<Value, Error> {
         match self.env.get_filter(filter) {
             Some(filter) => filter.apply_to(self, args),
-            None => Err(Error::from(ErrorKind::UnknownFilter)),
+            None => self.env.apply_unknown_filter(filter, self, args),
         }
     }
 
@@ -277,6 +303,37 @@ impl<'template, 'env> State<'template, 'env> {
         self.env.format(&value, self, &mut out).map(|_| rv)
     }
 
+    /// Looks up a temp value by name.
+    ///
+    /// Temps are scratch values that live for the duration of a single render.
+    /// They are shared between all filters, tests and functions invoked during
+    /// that render (including macros and included templates) but are not
+    /// visible to the template itself.  This is useful for filters that need
+    /// to keep some state around, for instance to generate unique IDs.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, State, value::Value};
+    /// # let mut env = Environment::new();
+    /// env.add_function("next_id", |state: &State| {
+    ///     let prev = state.get_temp("next_id").and_then(|x| i64::try_from(x).ok());
+    ///     let next = prev.unwrap_or(0) + 1;
+    ///     state.set_temp("next_id", Value::from(next));
+    ///     format!("id-{}", next)
+    /// });
+    /// let rv = env.render_str("{{ next_id() }} {{ next_id() }}", ()).unwrap();
+    /// assert_eq!(rv, "id-1 id-2");
+    /// ```
+    pub fn get_temp(&self, name: &str) -> Option<Value> {
+        self.temps.lock().unwrap().get(name).cloned()
+    }
+
+    /// Inserts a temp value, returning the previous value if there was one.
+    ///
+    /// For more information see [`get_temp`](Self::get_temp).
+    pub fn set_temp(&self, name: &str, value: Value) -> Option<Value> {
+        self.temps.lock().unwrap().insert(name.into(), value)
+    }
+
     /// Returns the fuel levels.
     ///
     /// When the fuel feature is enabled, during evaluation the template will keep
@@ -65,3 +65,25 @@ fn test_referenced_names_empty_bug() {
     let rv = instructions.get_referenced_names(0);
     assert!(rv.is_empty());
 }
+
+#[test]
+fn test_streaming_filter_codegen() {
+    use std::collections::BTreeSet;
+    use std::sync::Arc;
+
+    let ast = minijinja::machinery::parse("{{ a|upper }}{{ b|stream }}", "<unknown>").unwrap();
+    let mut c = CodeGenerator::new("<unknown>", "");
+    c.set_streaming_filters(Arc::new(BTreeSet::from(["stream".to_string()])));
+    c.compile_stmt(&ast);
+    let instructions = c.finish().0;
+
+    let ops = (0..instructions.len())
+        .filter_map(|idx| match instructions.get(idx) {
+            Some(Instruction::ApplyFilter(name, ..)) => Some(format!("apply {name}")),
+            Some(Instruction::EmitFilter(name, ..)) => Some(format!("emit {name}")),
+            Some(Instruction::Emit) => Some("emit".to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(ops, vec!["apply upper", "emit", "emit stream"]);
+}
@@ -2,8 +2,8 @@ use std::collections::BTreeMap;
 
 use similar_asserts::assert_eq;
 
-use minijinja::value::Value;
-use minijinja::Environment;
+use minijinja::value::{Kwargs, Serializable, Value};
+use minijinja::{AutoEscape, Environment, Error, State};
 
 #[test]
 fn test_basic() {
@@ -40,6 +40,25 @@ fn test_expression_owned() {
     assert_eq!(expr.eval(&ctx).unwrap(), Value::from(65));
 }
 
+#[test]
+fn test_eval_expr() {
+    let env = Environment::new();
+    let mut ctx = BTreeMap::new();
+    ctx.insert("a", 1);
+    ctx.insert("b", 2);
+    assert_eq!(env.eval_expr("a + b * 2", &ctx).unwrap(), Value::from(5));
+
+    // the expression source does not need to outlive the environment
+    for op in ["+", "-"] {
+        let expr = format!("a {op} b");
+        let rv = env.eval_expr(&expr, &ctx).unwrap();
+        assert_eq!(rv, Value::from(if op == "+" { 3 } else { -1 }));
+    }
+
+    let err = env.eval_expr("a +", &ctx).unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::SyntaxError);
+}
+
 #[test]
 fn test_expression_bug() {
     let env = Environment::new();
@@ -97,6 +116,192 @@ fn test_globals() {
     assert_eq!(tmpl.render(()).unwrap(), "42");
 }
 
+#[test]
+fn test_function_with_state_and_kwargs() {
+    fn greet(state: &State, name: &str, kwargs: Kwargs) -> Result<String, Error> {
+        let greeting = kwargs.get::<Option<&str>>("greeting")?.unwrap_or("Hello");
+        kwargs.assert_all_used()?;
+        Ok(format!("{} {} from {}!", greeting, name, state.name()))
+    }
+
+    let mut env = Environment::new();
+    env.add_function("greet", greet);
+    assert_eq!(
+        env.render_str("{{ greet('World') }}", ()).unwrap(),
+        "Hello World from <string>!"
+    );
+    assert_eq!(
+        env.render_str("{{ greet('World', greeting='Hi') }}", ())
+            .unwrap(),
+        "Hi World from <string>!"
+    );
+    assert!(env
+        .render_str("{{ greet('World', bad=true) }}", ())
+        .is_err());
+}
+
+#[test]
+fn test_filter_returning_serializable() {
+    #[derive(serde::Serialize)]
+    struct User {
+        name: String,
+        admin: bool,
+    }
+
+    fn users(names: Vec<String>) -> Result<Serializable<Vec<User>>, Error> {
+        Ok(Serializable(
+            names
+                .into_iter()
+                .map(|name| User {
+                    admin: name == "root",
+                    name,
+                })
+                .collect(),
+        ))
+    }
+
+    let mut env = Environment::new();
+    env.add_filter("users", users);
+    assert_eq!(
+        env.render_str(
+            "{% for u in ['root', 'peter']|users %}[{{ u.name }}:{{ u.admin }}]{% endfor %}",
+            ()
+        )
+        .unwrap(),
+        "[root:true][peter:false]"
+    );
+}
+
+#[test]
+fn test_undefined_access_callback() {
+    use std::sync::{Arc, Mutex};
+
+    let accessed = Arc::new(Mutex::new(Vec::new()));
+    let mut env = Environment::new();
+    env.set_undefined_access_callback({
+        let accessed = accessed.clone();
+        move |state, name, line| {
+            accessed.lock().unwrap().push(format!(
+                "{}:{}:{}",
+                state.name(),
+                line.unwrap_or(0),
+                name
+            ));
+        }
+    });
+    env.add_template(
+        "test.html",
+        "{{ user.name }}|{{ user.missing }}\n{{ unknown }}|{{ seq[0] }}",
+    )
+    .unwrap();
+    let rv = env
+        .get_template("test.html")
+        .unwrap()
+        .render(minijinja::context! {
+            user => minijinja::context! { name => "Peter" },
+            seq => vec![1],
+        })
+        .unwrap();
+    assert_eq!(rv, "Peter|\n|1");
+    assert_eq!(
+        *accessed.lock().unwrap(),
+        vec!["test.html:1:missing", "test.html:2:unknown"]
+    );
+}
+
+#[test]
+#[cfg(feature = "builtins")]
+fn test_render_all() {
+    let mut env = Environment::new();
+    env.add_template("a", "A: {{ name }}").unwrap();
+    env.add_template("b", "B: {{ name|upper }}").unwrap();
+    env.add_template("c", "C: {{ name }}!").unwrap();
+
+    let rv = env.render_all(
+        &["a", "b", "missing", "c"],
+        minijinja::context! { name => "site" },
+    );
+    let rv = rv
+        .into_iter()
+        .map(|(name, result)| (name, result.map_err(|err| err.kind())))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rv,
+        vec![
+            ("a".to_string(), Ok("A: site".to_string())),
+            ("b".to_string(), Ok("B: SITE".to_string())),
+            (
+                "missing".to_string(),
+                Err(minijinja::ErrorKind::TemplateNotFound)
+            ),
+            ("c".to_string(), Ok("C: site!".to_string())),
+        ]
+    );
+}
+
+#[test]
+fn test_auto_escape_extensions() {
+    let mut env = Environment::new();
+    env.set_auto_escape_callback(minijinja::default_auto_escape_callback);
+    env.set_auto_escape_extensions(&[("svg", AutoEscape::Html)]);
+    for name in ["icon.svg", "icon.svg.j2", "page.html", "plain.txt"] {
+        env.add_template(name, "<text>{{ value }}</text>").unwrap();
+    }
+    let render = |name| {
+        env.get_template(name)
+            .unwrap()
+            .render(minijinja::context! { value => "<b>" })
+            .unwrap()
+    };
+    assert_eq!(render("icon.svg"), "<text>&lt;b&gt;</text>");
+    assert_eq!(render("icon.svg.j2"), "<text>&lt;b&gt;</text>");
+    assert_eq!(render("page.html"), "<text>&lt;b&gt;</text>");
+    assert_eq!(render("plain.txt"), "<text><b></text>");
+}
+
+#[test]
+fn test_default_auto_escape() {
+    let mut env = Environment::new();
+    env.set_default_auto_escape(AutoEscape::Html);
+    for name in ["page.html", "plain.txt", "noext"] {
+        env.add_template(name, "{{ value }}").unwrap();
+    }
+    for name in ["page.html", "plain.txt", "noext"] {
+        let rv = env
+            .get_template(name)
+            .unwrap()
+            .render(minijinja::context! { value => "<b>" })
+            .unwrap();
+        assert_eq!(rv, "&lt;b&gt;");
+    }
+    assert_eq!(
+        env.render_str("{{ value }}", minijinja::context! { value => "<b>" })
+            .unwrap(),
+        "&lt;b&gt;"
+    );
+}
+
+#[test]
+fn test_render_str_autoescaped() {
+    let env = Environment::new();
+    let source = "<a title=\"{{ title }}\">{{ body }}</a>";
+    let ctx = minijinja::context! { title => "\"quoted\"", body => "<b>bold</b>" };
+    assert_eq!(
+        env.render_str_autoescaped(source, &ctx, AutoEscape::Html)
+            .unwrap(),
+        "<a title=\"&quot;quoted&quot;\">&lt;b&gt;bold&lt;&#x2f;b&gt;</a>"
+    );
+    assert_eq!(
+        env.render_str_autoescaped(source, &ctx, AutoEscape::None)
+            .unwrap(),
+        "<a title=\"\"quoted\"\"><b>bold</b></a>"
+    );
+    assert_eq!(
+        env.render_str(source, &ctx).unwrap(),
+        "<a title=\"\"quoted\"\"><b>bold</b></a>"
+    );
+}
+
 #[test]
 fn test_template_removal() {
     let mut env = Environment::new();
@@ -128,6 +333,86 @@ fn test_path_join() {
     assert_eq!(t.render(()).unwrap(), "bar.txt");
 }
 
+#[test]
+fn test_keep_comments() {
+    let source = "# generated\n{# do not edit #}\nvalue = {{ value }}\n  {#- trimmed -#}  !";
+    let mut env = Environment::new();
+    assert!(!env.keep_comments());
+    assert_eq!(
+        env.render_str(source, minijinja::context! { value => 42 })
+            .unwrap(),
+        "# generated\n\nvalue = 42!"
+    );
+
+    env.set_keep_comments(true);
+    assert!(env.keep_comments());
+    assert_eq!(
+        env.render_str(source, minijinja::context! { value => 42 })
+            .unwrap(),
+        "# generated\n{# do not edit #}\nvalue = 42{#- trimmed -#}!"
+    );
+}
+
+#[test]
+#[cfg(feature = "builtins")]
+fn test_line_statements() {
+    let mut env = Environment::new();
+    env.set_line_statement_prefix(Some("#".into()));
+    assert_eq!(env.line_statement_prefix(), Some("#"));
+    env.add_template(
+        "list.txt",
+        "<ul>\n  # for item in seq:\n  <li>{{ item }}</li>\n  # endfor\n</ul>\n\
+         # set total = seq|sum(\n    start=10)\n{{ total }} #{{ seq|length }}",
+    )
+    .unwrap();
+    let tmpl = env.get_template("list.txt").unwrap();
+    assert_eq!(
+        tmpl.render(minijinja::context! { seq => vec![1, 2] })
+            .unwrap(),
+        "<ul>\n  <li>1</li>\n  <li>2</li>\n</ul>\n13 #2"
+    );
+    assert_eq!(
+        tmpl.undeclared_variables(false),
+        ["seq"].into_iter().map(|x| x.to_string()).collect()
+    );
+    assert_eq!(
+        env.render_str("{% if true %}yes{% endif %}\n# if false\nno\n# endif", ())
+            .unwrap(),
+        "yes\n"
+    );
+
+    env.set_line_statement_prefix(None);
+    assert_eq!(
+        env.render_str("# not a statement", ()).unwrap(),
+        "# not a statement"
+    );
+}
+
+#[test]
+fn test_line_comments() {
+    let mut env = Environment::new();
+    env.set_line_comment_prefix(Some("##".into()));
+    assert_eq!(env.line_comment_prefix(), Some("##"));
+    assert_eq!(
+        env.render_str(
+            "## header comment\nvalue = {{ value }}   ## trailing comment\n#{{ value }}",
+            minijinja::context! { value => 42 }
+        )
+        .unwrap(),
+        "\nvalue = 42\n#42"
+    );
+
+    env.set_line_statement_prefix(Some("#".into()));
+    assert_eq!(
+        env.render_str(
+            "# for x in [1, 2]  ## loop over numbers\n{{ x }}\n## ignored\n# endfor",
+            ()
+        )
+        .unwrap(),
+        "1\n\n2\n\n"
+    );
+}
+
 #[test]
 fn test_keep_trailing_newlines() {
     let mut env = Environment::new();
@@ -149,3 +434,149 @@ fn test_keep_trailing_newlines() {
     );
     assert_eq!(env.render_str("blub\r\n", ()).unwrap(), "blub\r\n");
 }
+
+#[test]
+#[cfg(feature = "builtins")]
+fn test_map_key_order() {
+    use minijinja::MapKeyOrder;
+
+    let ctx = minijinja::context! {
+        map => Value::from_iter([("b", 1), ("c", 2), ("a", 3)]),
+    };
+    let tmpl = "{% for k in map %}{{ k }}{% endfor %}|\
+                {% for k, v in map|items %}{{ k }}={{ v }}{% endfor %}|\
+                {% for k, v in map|dictsort(reverse=true) %}{{ k }}{% endfor %}";
+
+    let mut env = Environment::new();
+    assert_eq!(env.map_key_order(), MapKeyOrder::Insertion);
+    #[cfg(feature = "preserve_order")]
+    {
+        assert_eq!(env.render_str(tmpl, &ctx).unwrap(), "bca|b=1c=2a=3|cba");
+    }
+    #[cfg(not(feature = "preserve_order"))]
+    {
+        assert_eq!(env.render_str(tmpl, &ctx).unwrap(), "abc|a=3b=1c=2|cba");
+    }
+
+    env.set_map_key_order(MapKeyOrder::Sorted);
+    assert_eq!(env.render_str(tmpl, &ctx).unwrap(), "abc|a=3b=1c=2|cba");
+}
+
+#[test]
+#[cfg(feature = "builtins")]
+fn test_add_global_serialize() {
+    #[derive(serde::Serialize)]
+    struct Config {
+        name: String,
+        debug: bool,
+        tags: Vec<&'static str>,
+    }
+
+    let mut env = Environment::new();
+    env.add_global_serialize(
+        "config",
+        &Config {
+            name: "demo".into(),
+            debug: true,
+            tags: vec!["a", "b"],
+        },
+    );
+    let rv = env
+        .render_str(
+            "{{ config.name }}|{{ config.debug }}|{{ config.tags|join(',') }}",
+            (),
+        )
+        .unwrap();
+    assert_eq!(rv, "demo|true|a,b");
+}
+
+#[test]
+fn test_format_for_kind() {
+    let mut env = Environment::new();
+    env.set_format_for_kind(
+        minijinja::value::ValueKind::Number,
+        |value| match f64::try_from(value.clone()) {
+            Ok(num) if num.fract() != 0.0 => format!("{num:.2}"),
+            _ => value.to_string(),
+        },
+    );
+    let ctx = minijinja::context! { price => 4.5, count => 3, name => "<b>" };
+    assert_eq!(
+        env.render_named_str("x.html", "{{ price }}|{{ count }}|{{ name }}", &ctx)
+            .unwrap(),
+        "4.50|3|&lt;b&gt;"
+    );
+
+    // the formatted string is written as-is and not auto escaped
+    env.set_format_for_kind(minijinja::value::ValueKind::Bool, |value| {
+        if value.is_true() {
+            "<b>yes</b>"
+        } else {
+            "<i>no</i>"
+        }
+        .into()
+    });
+    assert_eq!(
+        env.render_named_str("x.html", "{{ true }} {{ false }}", ())
+            .unwrap(),
+        "<b>yes</b> <i>no</i>"
+    );
+
+    // numbers stay numbers in json
+    #[cfg(feature = "json")]
+    {
+        assert_eq!(
+            env.render_named_str("x.json", "[{{ price }}, {{ count }}]", &ctx)
+                .unwrap(),
+            "[4.50, 3]"
+        );
+    }
+}
+
+#[test]
+fn test_collapse_whitespace() {
+    let mut env = Environment::new();
+    env.add_template(
+        "page.html",
+        "<ul>\n  {%- for item in items %}\n    <li>  {{ item }}\t</li>\n  {%- endfor %}\n</ul>",
+    )
+    .unwrap();
+    let ctx = minijinja::context! { items => vec!["a  b", "c"] };
+
+    let raw = env.get_template("page.html").unwrap().render(&ctx).unwrap();
+    assert_eq!(
+        raw,
+        "<ul>\n    <li>  a  b\t</li>\n    <li>  c\t</li>\n</ul>"
+    );
+
+    env.set_collapse_whitespace(true);
+    let tmpl = env.get_template("page.html").unwrap();
+    let collapsed = tmpl.render(&ctx).unwrap();
+    assert_eq!(collapsed, "<ul>\n <li> a b </li>\n <li> c </li>\n</ul>");
+
+    let mut buf = Vec::new();
+    tmpl.render_to_write(&ctx, &mut buf).unwrap();
+    assert_eq!(String::from_utf8(buf).unwrap(), collapsed);
+}
+
+#[test]
+fn test_loop_length_buffering() {
+    let mut env = Environment::new();
+    env.add_template(
+        "loop.txt",
+        "{% for x in seq %}{{ x }}{% if loop.last %}!{% else %},{% endif %}\
+         {{ loop.length }}{% endfor %}",
+    )
+    .unwrap();
+    let ctx = minijinja::context! {
+        seq => Value::make_iterable(|| (0..3).map(|x| Value::from(x * 2))),
+    };
+
+    assert!(!env.loop_length_buffering());
+    let tmpl = env.get_template("loop.txt").unwrap();
+    assert_eq!(tmpl.render(&ctx).unwrap(), "0,2,4,");
+
+    env.set_loop_length_buffering(true);
+    let tmpl = env.get_template("loop.txt").unwrap();
+    assert_eq!(tmpl.render(&ctx).unwrap(), "0,32,34!3");
+}
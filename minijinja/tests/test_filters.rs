@@ -1,6 +1,6 @@
 #![cfg(feature = "builtins")]
-use minijinja::value::Value;
-use minijinja::{args, Environment};
+use minijinja::value::{Kwargs, Value};
+use minijinja::{args, context, Environment};
 use similar_asserts::assert_eq;
 
 use minijinja::filters::{abs, indent};
@@ -31,23 +31,136 @@ fn test_filter_with_non() {
     assert_eq!(rv, Value::from("[wat]"));
 }
 
+#[test]
+fn test_filter_returning_option() {
+    fn lookup(map: Value, key: String) -> Option<String> {
+        map.get_attr(&key)
+            .ok()
+            .filter(|x| !x.is_undefined())
+            .map(|x| x.to_string())
+    }
+
+    let mut env = Environment::new();
+    env.add_filter("lookup", lookup);
+    let ctx = context! { map => context! { a => 1 } };
+
+    let rv = env
+        .render_str("{{ map|lookup('a') }}", ctx.clone())
+        .unwrap();
+    assert_eq!(rv, "1");
+    let rv = env
+        .render_str(
+            "{{ map|lookup('b') is undefined }}|{{ map|lookup('b')|default('missing') }}",
+            ctx,
+        )
+        .unwrap();
+    assert_eq!(rv, "true|missing");
+}
+
+#[test]
+fn test_filter_uses_state_auto_escape_and_env() {
+    use minijinja::{AutoEscape, State};
+
+    fn emphasize(state: &State, value: String) -> Result<Value, minijinja::Error> {
+        let rv = state
+            .env()
+            .get_template("emphasis.txt")?
+            .render(context! { value })?;
+        Ok(match state.auto_escape() {
+            AutoEscape::Html => {
+                Value::from_safe_string(format!("<em>{}</em>", minijinja::HtmlEscape(&rv)))
+            }
+            _ => Value::from(rv),
+        })
+    }
+
+    let mut env = Environment::new();
+    env.add_filter("emphasize", emphasize);
+    env.add_template("emphasis.txt", "*{{ value }}*").unwrap();
+    env.add_template("page.html", "{{ 'a<b'|emphasize }}")
+        .unwrap();
+    env.add_template("page.txt", "{{ 'a<b'|emphasize }}")
+        .unwrap();
+    env.add_template(
+        "plain.html",
+        "{% autoescape false %}{{ 'a<b'|emphasize }}{% endautoescape %}",
+    )
+    .unwrap();
+
+    let render = |name| env.get_template(name).unwrap().render(()).unwrap();
+    assert_eq!(render("page.html"), "<em>*a&lt;b*</em>");
+    assert_eq!(render("page.txt"), "*a<b*");
+    assert_eq!(render("plain.html"), "*a<b*");
+}
+
+#[test]
+fn test_enum_variant() {
+    #[derive(serde::Serialize)]
+    #[allow(unused)]
+    enum Shape {
+        Empty,
+        Square(u32),
+        Point(i32, i32),
+        Circle { radius: u32 },
+    }
+
+    #[derive(serde::Serialize)]
+    #[serde(tag = "type")]
+    #[allow(unused)]
+    enum Event {
+        Click { x: i32 },
+        Close,
+    }
+
+    let env = Environment::new();
+    let tmpl = env
+        .template_from_str(
+            "{% for shape in shapes %}{{ shape|enum_variant }}\
+             {% if shape|enum_variant == 'Circle' %}({{ shape.Circle.radius }}){% endif %};\
+             {% endfor %}|{% for event in events %}{{ event|enum_variant(tag='type') }};{% endfor %}",
+        )
+        .unwrap();
+    let rv = tmpl
+        .render(context! {
+            shapes => vec![
+                Shape::Empty,
+                Shape::Square(2),
+                Shape::Point(1, 2),
+                Shape::Circle { radius: 3 },
+            ],
+            events => vec![Event::Click { x: 1 }, Event::Close],
+        })
+        .unwrap();
+    assert_eq!(rv, "Empty;Square;Point;Circle(3);|Click;Close;");
+}
+
+fn no_kwargs() -> Kwargs {
+    Kwargs::from_iter(Vec::<(&str, Value)>::new())
+}
+
 #[test]
 fn test_indent_one_empty_line() {
     let teststring = String::from("\n");
-    assert_eq!(indent(teststring, 2, None, None), String::from(""));
+    assert_eq!(
+        indent(teststring, 2, None, None, no_kwargs()).unwrap(),
+        String::from("")
+    );
 }
 
 #[test]
 fn test_indent_one_line() {
     let teststring = String::from("test\n");
-    assert_eq!(indent(teststring, 2, None, None), String::from("test"));
+    assert_eq!(
+        indent(teststring, 2, None, None, no_kwargs()).unwrap(),
+        String::from("test")
+    );
 }
 
 #[test]
 fn test_indent() {
     let teststring = String::from("test\ntest1\n\ntest2\n");
     assert_eq!(
-        indent(teststring, 2, None, None),
+        indent(teststring, 2, None, None, no_kwargs()).unwrap(),
         String::from("test\n  test1\n\n  test2")
     );
 }
@@ -56,7 +169,7 @@ fn test_indent() {
 fn test_indent_with_indented_first_line() {
     let teststring = String::from("test\ntest1\n\ntest2\n");
     assert_eq!(
-        indent(teststring, 2, Some(true), None),
+        indent(teststring, 2, Some(true), None, no_kwargs()).unwrap(),
         String::from("  test\n  test1\n\n  test2")
     );
 }
@@ -65,16 +178,47 @@ fn test_indent_with_indented_first_line() {
 fn test_indent_with_indented_blank_line() {
     let teststring = String::from("test\ntest1\n\ntest2\n");
     assert_eq!(
-        indent(teststring, 2, None, Some(true)),
+        indent(teststring, 2, None, Some(true), no_kwargs()).unwrap(),
         String::from("test\n  test1\n  \n  test2")
     );
 }
 
+#[test]
+fn test_indent_skip_empty() {
+    let teststring = String::from("test\n  \n\ntest2\n");
+    assert_eq!(
+        indent(teststring.clone(), 2, None, None, no_kwargs()).unwrap(),
+        String::from("test\n    \n\n  test2")
+    );
+    assert_eq!(
+        indent(
+            teststring.clone(),
+            2,
+            None,
+            Some(false),
+            Kwargs::from_iter([("skip_empty", Value::from(true))])
+        )
+        .unwrap(),
+        String::from("test\n  \n\n  test2")
+    );
+    assert_eq!(
+        indent(
+            teststring,
+            2,
+            None,
+            Some(true),
+            Kwargs::from_iter([("skip_empty", Value::from(true))])
+        )
+        .unwrap(),
+        String::from("test\n  \n  \n  test2")
+    );
+}
+
 #[test]
 fn test_indent_with_all_indented() {
     let teststring = String::from("test\ntest1\n\ntest2\n");
     assert_eq!(
-        indent(teststring, 2, Some(true), Some(true)),
+        indent(teststring, 2, Some(true), Some(true), no_kwargs()).unwrap(),
         String::from("  test\n  test1\n  \n  test2")
     );
 }
@@ -86,3 +230,340 @@ fn test_abs_overflow() {
     let err = abs(Value::from(i128::MIN)).unwrap_err();
     assert_eq!(err.to_string(), "invalid operation: overflow on abs");
 }
+
+#[test]
+fn test_first_last_random_access() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use minijinja::value::SeqObject;
+
+    struct CountingSeq(Arc<AtomicUsize>);
+
+    impl SeqObject for CountingSeq {
+        fn get_item(&self, idx: usize) -> Option<Value> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            (idx < 100).then(|| Value::from(idx))
+        }
+
+        fn item_count(&self) -> usize {
+            100
+        }
+    }
+
+    let counter = Arc::new(AtomicUsize::new(0));
+    let seq = Value::from_seq_object(CountingSeq(counter.clone()));
+    let env = Environment::new();
+    let state = env.empty_state();
+
+    let rv = state.apply_filter("first", args!(seq.clone())).unwrap();
+    assert_eq!(rv, Value::from(0));
+    assert_eq!(counter.swap(0, Ordering::Relaxed), 1);
+
+    let rv = state.apply_filter("last", args!(seq)).unwrap();
+    assert_eq!(rv, Value::from(99));
+    assert_eq!(counter.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn test_first_last_empty_seq() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use minijinja::value::SeqObject;
+
+    // an empty sequence that would still hand out items if asked
+    struct EmptySeq(Arc<AtomicUsize>);
+
+    impl SeqObject for EmptySeq {
+        fn get_item(&self, idx: usize) -> Option<Value> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Some(Value::from(idx))
+        }
+
+        fn item_count(&self) -> usize {
+            0
+        }
+    }
+
+    let counter = Arc::new(AtomicUsize::new(0));
+    let seq = Value::from_seq_object(EmptySeq(counter.clone()));
+    let env = Environment::new();
+    let state = env.empty_state();
+
+    let rv = state.apply_filter("first", args!(seq.clone())).unwrap();
+    assert!(rv.is_undefined());
+    let rv = state.apply_filter("last", args!(seq)).unwrap();
+    assert!(rv.is_undefined());
+    assert_eq!(counter.load(Ordering::Relaxed), 0);
+}
+
+#[test]
+#[cfg(feature = "markdown")]
+fn test_markdown() {
+    let env = Environment::new();
+    let rv = env
+        .render_str(
+            "{{ text|markdown }}",
+            context!(text => "# Hello\n\n*World* <b>x</b>"),
+        )
+        .unwrap();
+    assert_eq!(
+        rv,
+        "<h1>Hello</h1>\n<p><em>World</em> &lt;b&gt;x&lt;/b&gt;</p>\n"
+    );
+
+    let rv = env
+        .render_str(
+            "{{ text|markdown(sanitize=false) }}",
+            context!(text => "*World* <b>x</b>"),
+        )
+        .unwrap();
+    assert_eq!(rv, "<p><em>World</em> <b>x</b></p>\n");
+
+    let rv = env
+        .render_str(
+            "{{ text|markdown }}",
+            context!(text => "[click](javascript:alert(1))"),
+        )
+        .unwrap();
+    assert_eq!(rv, "<p><a href=\"\">click</a></p>\n");
+}
+
+#[test]
+fn test_nl2br() {
+    let env = Environment::new();
+    let rv = env
+        .render_named_str(
+            "comment.html",
+            "{{ comment|nl2br }}|{{ trusted|safe|nl2br }}",
+            context! {
+                comment => "<b>hi</b>\r\nthere & you\n",
+                trusted => "<i>a</i>\nb",
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        rv,
+        "&lt;b&gt;hi&lt;&#x2f;b&gt;<br>\nthere &amp; you<br>\n|<i>a</i><br>\nb"
+    );
+}
+
+#[test]
+fn test_xmlattr_key_order() {
+    let env = Environment::new();
+    let rv = env
+        .render_str("<ul{{ {'title': 'x', 'class': 'my list'}|xmlattr }}>", ())
+        .unwrap();
+    if cfg!(feature = "preserve_order") {
+        assert_eq!(rv, r#"<ul title="x" class="my list">"#);
+    } else {
+        assert_eq!(rv, r#"<ul class="my list" title="x">"#);
+    }
+}
+
+#[test]
+fn test_xmlattr_invalid_key() {
+    let env = Environment::new();
+    let err = env
+        .render_str("{{ {'on click': 'x'}|xmlattr }}", ())
+        .unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::InvalidOperation);
+    assert_eq!(
+        err.detail(),
+        Some("invalid character in attribute name: \"on click\"")
+    );
+}
+
+#[test]
+fn test_escape_idempotent() {
+    let env = Environment::new();
+    let render = |source: &str| {
+        env.render_named_str(
+            "page.html",
+            source,
+            context! { text => r#"<a href="x">Tom & 'Jerry'</a>"# },
+        )
+        .unwrap()
+    };
+
+    let escaped = "&lt;a href=&quot;x&quot;&gt;Tom &amp; &#x27;Jerry&#x27;&lt;&#x2f;a&gt;";
+    assert_eq!(render("{{ text|e }}"), escaped);
+    assert_eq!(render("{{ text|escape|escape }}"), escaped);
+    assert_eq!(render("{{ text|e|e|e }}"), escaped);
+    assert_eq!(render("{{ (text|e) is safe }}"), "true");
+    assert_eq!(
+        render("{{ text|safe|e }}"),
+        r#"<a href="x">Tom & 'Jerry'</a>"#
+    );
+    assert_eq!(render("{{ (text|safe|e) is safe }}"), "true");
+}
+
+#[test]
+fn test_sum_non_numeric() {
+    let env = Environment::new();
+    let err = env
+        .render_str("{{ [1, 'two']|sum }}", context! {})
+        .unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::InvalidOperation);
+    assert_eq!(err.detail(), Some("cannot sum value of type string"));
+
+    let err = env
+        .render_str(
+            "{{ items|sum(attribute='price') }}",
+            context! {
+                items => vec![context! { price => "free" }]
+            },
+        )
+        .unwrap_err();
+    assert_eq!(err.detail(), Some("cannot sum value of type string"));
+}
+
+#[test]
+fn test_unknown_filter() {
+    let mut env = Environment::new();
+    let err = env.render_str("{{ 'x'|uper }}", ()).unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::UnknownFilter);
+    assert_eq!(
+        err.detail(),
+        Some("filter uper is unknown (did you mean upper?)")
+    );
+    let err = env.render_str("{{ 'x'|nothing_like_it }}", ()).unwrap_err();
+    assert_eq!(err.detail(), Some("filter nothing_like_it is unknown"));
+
+    env.set_unknown_filter_callback(|name, _state, args| {
+        Ok(name
+            .strip_prefix("repeat_")
+            .and_then(|count| count.parse::<usize>().ok())
+            .map(|count| Value::from(args[0].to_string().repeat(count))))
+    });
+    assert_eq!(env.render_str("{{ 'ab'|repeat_3 }}", ()).unwrap(), "ababab");
+    let err = env.render_str("{{ 'x'|uper }}", ()).unwrap_err();
+    assert_eq!(
+        err.detail(),
+        Some("filter uper is unknown (did you mean upper?)")
+    );
+}
+
+#[test]
+fn test_streaming_filter() {
+    use minijinja::value::from_args;
+
+    let mut env = Environment::new();
+    env.add_streaming_filter("numbered_lines", |_state, out, args| {
+        let (count,): (usize,) = from_args(args)?;
+        for idx in 0..count {
+            writeln!(out, "<li>{}</li>", idx)?;
+        }
+        Ok(())
+    });
+    env.add_template("list.html", "<ul>\n{{ count|numbered_lines }}</ul>")
+        .unwrap();
+    env.add_template(
+        "captured.html",
+        "{% set items = 2|numbered_lines %}{{ items is safe }}\n{{ items }}",
+    )
+    .unwrap();
+
+    let rv = env
+        .get_template("list.html")
+        .unwrap()
+        .render(context! { count => 100_000 })
+        .unwrap();
+    assert_eq!(rv.lines().count(), 100_002);
+    assert!(rv.starts_with("<ul>\n<li>0</li>\n<li>1</li>\n"));
+    assert!(rv.ends_with("<li>99999</li>\n</ul>"));
+
+    // used anywhere but directly in a print statement, the output
+    // is captured and marked as safe.
+    let rv = env
+        .get_template("captured.html")
+        .unwrap()
+        .render(())
+        .unwrap();
+    assert_eq!(rv, "true\n<li>0</li>\n<li>1</li>\n");
+}
+
+#[test]
+fn test_int_invalid_base() {
+    let env = Environment::new();
+    let err = env.render_str("{{ '10'|int(base=37) }}", ()).unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::InvalidOperation);
+    assert_eq!(err.detail(), Some("base must be 0 or between 2 and 36"));
+}
+
+#[test]
+fn test_filter_delegation() {
+    use minijinja::State;
+
+    let mut env = Environment::new();
+    // applies the first registered filter of the given names
+    env.add_filter(
+        "first_available",
+        |state: &State, value: Value, names: Vec<String>| -> Result<Value, minijinja::Error> {
+            match names.iter().find_map(|name| state.env().get_filter(name)) {
+                Some(filter) => filter.apply_to(state, &[value]),
+                None => Ok(value),
+            }
+        },
+    );
+    env.add_filter("shout", |value: String| {
+        format!("{}!", value.to_uppercase())
+    });
+
+    assert!(env.has_filter("shout"));
+    assert!(!env.has_filter("whisper"));
+    assert!(env.has_test("even"));
+    assert!(!env.has_test("whisper"));
+    assert!(env.get_filter("whisper").is_none());
+    let state = env.empty_state();
+    let even = env.get_test("even").unwrap();
+    assert!(even.perform(&state, &[Value::from(2)]).unwrap());
+    assert!(!even.perform(&state, &[Value::from(3)]).unwrap());
+    assert_eq!(
+        env.render_str("{{ 'hi'|first_available(['whisper', 'shout']) }}", ())
+            .unwrap(),
+        "HI!"
+    );
+    assert_eq!(
+        env.render_str("{{ 'hi'|first_available(['whisper']) }}", ())
+            .unwrap(),
+        "hi"
+    );
+}
+
+#[test]
+fn test_filter_error_source() {
+    use std::error::Error as _;
+
+    fn parse_int(value: &str) -> Result<i64, minijinja::Error> {
+        value.parse().map_err(|err| {
+            minijinja::Error::new(minijinja::ErrorKind::InvalidOperation, "not an integer")
+                .with_source(err)
+        })
+    }
+
+    let mut env = Environment::new();
+    env.add_filter("parse_int", parse_int);
+    assert_eq!(env.render_str("{{ '42'|parse_int }}", ()).unwrap(), "42");
+
+    let err = env.render_str("{{ 'x'|parse_int }}", ()).unwrap_err();
+    assert_eq!(err.detail(), Some("not an integer"));
+    let source = err.source().unwrap();
+    assert!(source.is::<std::num::ParseIntError>());
+    assert_eq!(source.to_string(), "invalid digit found in string");
+    assert!(source.source().is_none());
+
+    // sources can be chained
+    let outer =
+        minijinja::Error::new(minijinja::ErrorKind::BadSerialization, "outer").with_source(err);
+    let mut chain = vec![outer.to_string()];
+    let mut err = &outer as &dyn std::error::Error;
+    while let Some(next_err) = err.source() {
+        chain.push(next_err.to_string());
+        err = next_err;
+    }
+    assert_eq!(chain.len(), 3);
+    assert!(chain[1].contains("not an integer"));
+    assert_eq!(chain[2], "invalid digit found in string");
+}
@@ -56,3 +56,95 @@ fn test_source_replace_dynamic() {
     let rv = env.get_template("a").unwrap().render(()).unwrap();
     assert_eq!(rv, "2");
 }
+
+#[test]
+fn test_loader_error() {
+    use minijinja::{Error, ErrorKind};
+
+    let mut env = Environment::new();
+    env.set_loader(|name| {
+        Err(Error::new(
+            ErrorKind::InvalidOperation,
+            format!("cannot load {}", name),
+        ))
+    });
+    let err = env.get_template("broken").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidOperation);
+    assert_eq!(err.detail(), Some("cannot load broken"));
+}
+
+#[test]
+fn test_owned_template_from_runtime_strings() {
+    let mut env = Environment::new();
+    for idx in 0..3 {
+        let name = format!("page_{}.txt", idx);
+        let source = format!("Page {} of {{{{ total }}}}", idx + 1);
+        env.add_template_owned(name, source).unwrap();
+    }
+    let t = env.get_template("page_1.txt").unwrap();
+    assert_eq!(
+        t.render(minijinja::context! { total => 3 }).unwrap(),
+        "Page 2 of 3"
+    );
+}
+
+#[test]
+fn test_chained_loaders() {
+    let mut env = Environment::new();
+    env.add_loader(|name| match name {
+        "a" => Ok(Some("first a".into())),
+        _ => Ok(None),
+    });
+    env.add_loader(|name| match name {
+        "a" => Ok(Some("second a".into())),
+        "b" => Ok(Some("second b".into())),
+        _ => Ok(None),
+    });
+    assert_eq!(
+        env.get_template("a").unwrap().render(()).unwrap(),
+        "first a"
+    );
+    assert_eq!(
+        env.get_template("b").unwrap().render(()).unwrap(),
+        "second b"
+    );
+    let err = env.get_template("c").unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::TemplateNotFound);
+
+    // set_loader replaces all loaders
+    let mut env = Environment::new();
+    env.add_loader(|_| Ok(Some("old".into())));
+    env.set_loader(|name| match name {
+        "a" => Ok(Some("new".into())),
+        _ => Ok(None),
+    });
+    assert_eq!(env.get_template("a").unwrap().render(()).unwrap(), "new");
+    assert!(env.get_template("b").is_err());
+}
+
+#[test]
+fn test_clear_caches() {
+    use std::sync::{Arc, Mutex};
+
+    let source = Arc::new(Mutex::new(String::from("Version 1")));
+    let mut env = Environment::new();
+    env.add_template("static", "Static").unwrap();
+    env.set_loader({
+        let source = source.clone();
+        move |name| match name {
+            "dynamic" => Ok(Some(source.lock().unwrap().clone())),
+            _ => Ok(None),
+        }
+    });
+
+    let render = |env: &Environment, name| env.get_template(name).unwrap().render(()).unwrap();
+    assert_eq!(render(&env, "dynamic"), "Version 1");
+
+    // the compiled template is cached until the caches are cleared
+    *source.lock().unwrap() = "Version 2".into();
+    assert_eq!(render(&env, "dynamic"), "Version 1");
+
+    env.clear_caches();
+    assert_eq!(render(&env, "dynamic"), "Version 2");
+    assert_eq!(render(&env, "static"), "Static");
+}
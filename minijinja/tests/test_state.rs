@@ -11,3 +11,34 @@ fn test_state_lookup_global() {
     let rv = env.render_str("[{{ lookup_global() }}]", ()).unwrap();
     assert_eq!(rv, "[true]");
 }
+
+#[test]
+#[cfg(feature = "macros")]
+fn test_state_temps() {
+    let mut env = Environment::new();
+    env.add_filter("unique_id", |state: &State, prefix: &str| -> String {
+        let counter = state
+            .get_temp("unique_id")
+            .and_then(|x| i64::try_from(x).ok())
+            .unwrap_or(0)
+            + 1;
+        state.set_temp("unique_id", Value::from(counter));
+        format!("{}-{}", prefix, counter)
+    });
+    env.add_template(
+        "macro.html",
+        "{% macro field(name) %}{{ name|unique_id }}{% endmacro %}",
+    )
+    .unwrap();
+    env.add_template(
+        "page.html",
+        "{% from 'macro.html' import field %}\
+         {{ 'a'|unique_id }} {{ 'b'|unique_id }} {{ field('c') }}",
+    )
+    .unwrap();
+
+    let tmpl = env.get_template("page.html").unwrap();
+    assert_eq!(tmpl.render(()).unwrap(), "a-1 b-2 c-3");
+    // temps do not survive across renders
+    assert_eq!(tmpl.render(()).unwrap(), "a-1 b-2 c-3");
+}
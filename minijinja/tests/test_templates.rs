@@ -33,6 +33,10 @@ fn test_vm() {
         let contents = std::fs::read_to_string(path).unwrap();
         let mut iter = contents.splitn(2, "\n---\n");
         let mut env = Environment::new();
+        // the markdown filter is feature gated and must not show up in the
+        // snapshots of templates that dump the environment.
+        #[cfg(feature = "markdown")]
+        env.remove_filter("markdown");
         let ctx: serde_json::Value = serde_json::from_str(iter.next().unwrap()).unwrap();
 
         for (path, source) in &refs {
@@ -313,6 +317,24 @@ fn test_loop_changed() {
     assert_eq!(rv, "12345");
 }
 
+#[test]
+fn test_debug_function() {
+    let rv = minijinja::render!(
+        r#"
+        {%- set greeting = "Hello" -%}
+        {%- for item in items -%}
+          {{ debug() }}
+        {%- endfor -%}
+        "#,
+        user => "Peter",
+        items => vec![42],
+    );
+    assert!(rv.contains(r#""user": "Peter""#));
+    assert!(rv.contains(r#""greeting": "Hello""#));
+    assert!(rv.contains(r#""item": 42"#));
+    assert!(rv.contains("\"loop\": Loop {"));
+}
+
 // ideally this would work, but unfortunately the way serde flatten works makes it
 // impossible for us to support with the internal optimizations in the value model.
 // see https://github.com/mitsuhiko/minijinja/issues/222
@@ -408,18 +430,65 @@ fn test_custom_syntax() {
         block_end: "}".into(),
         variable_start: "${".into(),
         variable_end: "}".into(),
-        comment_start: "{*".into(),
-        comment_end: "*}".into(),
+        comment_start: "/*".into(),
+        comment_end: "*/".into(),
     })
     .unwrap();
 
     // this on the other hand is okay
     let value = env
-        .render_str("{for x in range(3)}${x}{endfor}{* nothing *}", ())
+        .render_str("{for x in range(3)}${x}{endfor}/* nothing */", ())
         .unwrap();
     assert_eq!(value, r"012");
 }
 
+#[test]
+#[cfg(feature = "custom_syntax")]
+fn test_custom_syntax_validation() {
+    let mut env = Environment::new();
+
+    let err = env
+        .set_syntax(minijinja::Syntax {
+            block_start: "{{".into(),
+            variable_start: "{{".into(),
+            ..Default::default()
+        })
+        .unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::InvalidDelimiter);
+    assert_eq!(
+        err.detail(),
+        Some("block_start and variable_start delimiters must be different")
+    );
+
+    let err = env
+        .set_syntax(minijinja::Syntax {
+            block_start: "{%".into(),
+            variable_start: "{%%".into(),
+            ..Default::default()
+        })
+        .unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::InvalidDelimiter);
+    assert_eq!(
+        err.detail(),
+        Some("block_start and variable_start delimiters must not be prefixes of each other")
+    );
+
+    let err = env
+        .set_syntax(minijinja::Syntax {
+            comment_end: "".into(),
+            ..Default::default()
+        })
+        .unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::InvalidDelimiter);
+    assert_eq!(
+        err.detail(),
+        Some("comment_end delimiter must not be empty")
+    );
+
+    // the failed attempts left the default syntax in place
+    assert_eq!(env.render_str("{{ 1 + 1 }}", ()).unwrap(), "2");
+}
+
 #[test]
 fn test_undeclared_variables() {
     let mut env = Environment::new();
@@ -445,6 +514,32 @@ fn test_undeclared_variables() {
     );
 }
 
+#[test]
+fn test_undeclared_variables_excludes_locals() {
+    let mut env = Environment::new();
+    env.add_template(
+        "demo",
+        "{% for item in items %}{{ user.name }}{{ item.title }}{{ loop.index }}{% endfor %}\
+         {% macro m(arg) %}{{ arg }}{{ other }}{% endmacro %}{{ m(1) }}",
+    )
+    .unwrap();
+    let tmpl = env.get_template("demo").unwrap();
+    assert_eq!(
+        tmpl.undeclared_variables(false),
+        ["items", "user", "other"]
+            .into_iter()
+            .map(|x| x.to_string())
+            .collect()
+    );
+    assert_eq!(
+        tmpl.undeclared_variables(true),
+        ["items", "user.name", "other"]
+            .into_iter()
+            .map(|x| x.to_string())
+            .collect()
+    );
+}
+
 #[test]
 fn test_block_fragments() {
     let mut env = Environment::new();
@@ -462,6 +557,29 @@ fn test_block_fragments() {
     assert_eq!(rv_b, "foo");
 }
 
+#[test]
+fn test_block_names() {
+    let mut env = Environment::new();
+    env.add_template(
+        "layout",
+        "{% block title %}{% endblock %}{% block body %}{% block inner %}{% endblock %}{% endblock %}",
+    )
+    .unwrap();
+    env.add_template(
+        "child",
+        "{% extends 'layout' %}{% block title %}Child{% endblock %}",
+    )
+    .unwrap();
+    env.add_template("plain", "no blocks here").unwrap();
+
+    let layout = env.get_template("layout").unwrap();
+    assert_eq!(layout.block_names(), vec!["body", "inner", "title"]);
+    let child = env.get_template("child").unwrap();
+    assert_eq!(child.block_names(), vec!["title"]);
+    let plain = env.get_template("plain").unwrap();
+    assert!(plain.block_names().is_empty());
+}
+
 #[test]
 fn test_state() {
     let mut env = Environment::new();
@@ -522,3 +640,151 @@ fn test_render_to_write_state() {
     assert_eq!(state.lookup("foo"), Some(Value::from(42)));
     assert_eq!(state.call_macro("bar", &[]).ok().as_deref(), Some("x"));
 }
+
+#[test]
+fn test_max_include_depth() {
+    let mut env = Environment::new();
+    env.set_max_include_depth(1);
+    env.add_template("a", "a{% include 'b' %}").unwrap();
+    env.add_template("b", "b{% include 'c' %}").unwrap();
+    env.add_template("c", "c").unwrap();
+    env.add_template("layout", "[{% block body %}{% endblock %}]")
+        .unwrap();
+    env.add_template(
+        "child",
+        "{% extends 'layout' %}{% block body %}{% include 'b' %}{% endblock %}",
+    )
+    .unwrap();
+    env.add_template(
+        "recurse",
+        "{% macro r(n) %}{% if n > 0 %}{{ r(n - 1) }}{% else %}{% include 'c' %}{% endif %}{% endmacro %}{{ r(10) }}",
+    )
+    .unwrap();
+
+    assert_eq!(env.get_template("b").unwrap().render(()).unwrap(), "bc");
+    assert_eq!(
+        env.get_template("recurse").unwrap().render(()).unwrap(),
+        "c"
+    );
+
+    let err = env.get_template("a").unwrap().render(()).unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::BadInclude);
+    let source = std::error::Error::source(&err)
+        .unwrap()
+        .downcast_ref::<Error>()
+        .unwrap();
+    assert_eq!(
+        source.detail(),
+        Some("maximum include depth (1) exceeded when loading \"c\"")
+    );
+
+    let err = env.get_template("child").unwrap().render(()).unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::InvalidOperation);
+    assert_eq!(
+        err.detail(),
+        Some("maximum include depth (1) exceeded when loading \"b\"")
+    );
+}
+
+#[test]
+fn test_and_or_short_circuit() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let mut env = Environment::new();
+    env.add_function("side_effect", {
+        let calls = calls.clone();
+        move || -> Value {
+            calls.fetch_add(1, Ordering::Relaxed);
+            Value::from("called")
+        }
+    });
+
+    let rv = env
+        .render_str(
+            "{{ x or side_effect() }}|{{ y and side_effect() }}",
+            context! { x => "truthy", y => 0 },
+        )
+        .unwrap();
+    assert_eq!(rv, "truthy|0");
+    assert_eq!(calls.load(Ordering::Relaxed), 0);
+
+    let rv = env
+        .render_str(
+            "{{ x or side_effect() }}|{{ y and side_effect() }}",
+            context! { x => "", y => 1 },
+        )
+        .unwrap();
+    assert_eq!(rv, "called|called");
+    assert_eq!(calls.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn test_loop_length_on_lazy_seq() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use minijinja::value::SeqObject;
+
+    struct Squares {
+        count: usize,
+        computed: Arc<AtomicUsize>,
+    }
+
+    impl SeqObject for Squares {
+        fn get_item(&self, idx: usize) -> Option<Value> {
+            if idx < self.count {
+                self.computed.fetch_add(1, Ordering::Relaxed);
+                Some(Value::from(idx * idx))
+            } else {
+                None
+            }
+        }
+
+        fn item_count(&self) -> usize {
+            self.count
+        }
+    }
+
+    let computed = Arc::new(AtomicUsize::new(0));
+    let seq = Value::from_seq_object(Squares {
+        count: 4,
+        computed: computed.clone(),
+    });
+    let rv = Environment::new()
+        .render_str(
+            "{% for x in seq %}{{ x }}/{{ loop.length }}/{{ loop.revindex }}\
+             {% if not loop.last %}, {% endif %}{% endfor %}",
+            context! { seq },
+        )
+        .unwrap();
+    assert_eq!(rv, "0/4/4, 1/4/3, 4/4/2, 9/4/1");
+    assert_eq!(computed.load(Ordering::Relaxed), 4);
+}
+
+#[test]
+fn test_dynamic_extends() {
+    let mut env = Environment::new();
+    env.add_template("a.html", "A[{% block body %}{% endblock %}]")
+        .unwrap();
+    env.add_template("b.html", "B({% block body %}{% endblock %})")
+        .unwrap();
+    env.add_template(
+        "page.html",
+        "{% extends 'b.html' if alternative else layout %}{% block body %}{{ title }}{% endblock %}",
+    )
+    .unwrap();
+
+    let tmpl = env.get_template("page.html").unwrap();
+    assert_eq!(
+        tmpl.render(context! { layout => "a.html", alternative => false, title => "x" })
+            .unwrap(),
+        "A[x]"
+    );
+    assert_eq!(
+        tmpl.render(context! { layout => "a.html", alternative => true, title => "x" })
+            .unwrap(),
+        "B(x)"
+    );
+}
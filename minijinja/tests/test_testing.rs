@@ -0,0 +1,64 @@
+#![cfg(feature = "testing")]
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use minijinja::testing::{assert_renders, render_for_test};
+use minijinja::{context, Environment};
+
+fn make_env() -> Environment<'static> {
+    let mut env = Environment::new();
+    env.add_template(
+        "list.txt",
+        "{% for item in items %}- {{ item }}\n{% endfor %}",
+    )
+    .unwrap();
+    env.add_template("broken.txt", "{{ 1 + missing.attr }}")
+        .unwrap();
+    env
+}
+
+#[test]
+fn test_assert_renders() {
+    let env = make_env();
+    assert_renders(
+        &env,
+        "list.txt",
+        context! { items => vec!["a", "b"] },
+        "- a\n- b\n",
+    );
+    assert_eq!(
+        render_for_test(&env, "list.txt", context! { items => vec!["c"] }),
+        "- c\n"
+    );
+}
+
+#[test]
+fn test_assert_renders_mismatch() {
+    let env = make_env();
+    let err = catch_unwind(AssertUnwindSafe(|| {
+        assert_renders(
+            &env,
+            "list.txt",
+            context! { items => vec!["a", "x", "c"] },
+            "- a\n- b\n- c\n",
+        )
+    }))
+    .unwrap_err();
+    let msg = err.downcast_ref::<String>().unwrap();
+    assert_eq!(
+        msg,
+        "template \"list.txt\" rendered unexpected output:\n  \"- a\"\n+ \"- x\"\n- \"- b\"\n  \"- c\"\n  \"\"\n"
+    );
+}
+
+#[test]
+fn test_render_for_test_error() {
+    let env = make_env();
+    let err = catch_unwind(AssertUnwindSafe(|| {
+        render_for_test(&env, "broken.txt", context! {})
+    }))
+    .unwrap_err();
+    let msg = err.downcast_ref::<String>().unwrap();
+    assert!(msg.starts_with("failed to render template \"broken.txt\": "));
+    assert!(msg.contains("undefined value"));
+}
@@ -14,3 +14,24 @@ fn test_basics() {
     let state = env.empty_state();
     assert!(state.perform_test("test", args!(23, 23)).unwrap());
 }
+
+#[test]
+fn test_state_lookup_in_template() {
+    fn is_enabled(state: &State, feature: &str) -> bool {
+        state
+            .lookup("enabled_features")
+            .and_then(|features| features.contains(&feature.into()).ok())
+            .unwrap_or(false)
+    }
+
+    let mut env = Environment::new();
+    env.add_test("enabled", is_enabled);
+    let rv = env
+        .render_str(
+            "{% for f in ['a', 'b'] %}{{ f }}={{ f is enabled }} {% endfor %}\
+             {% with enabled_features = ['b'] %}{{ 'b' is enabled }}{% endwith %}",
+            minijinja::context! { enabled_features => vec!["a"] },
+        )
+        .unwrap();
+    assert_eq!(rv, "a=true b=false true");
+}
@@ -104,3 +104,25 @@ fn test_chainable_undefined() {
     assert_eq!(render!(in env, "<{{ undefined|test }}>"), "<>");
     assert_eq!(render!(in env, "{{ 42 in undefined }}"), "false");
 }
+
+#[test]
+fn test_undefined_render() {
+    let mut env = Environment::new();
+    env.set_undefined_render("[UNDEFINED]".to_string());
+
+    assert_eq!(render!(in env, "<{{ missing }}>"), "<[UNDEFINED]>");
+    assert_eq!(
+        render!(in env, "<{{ true.missing_attribute }}>"),
+        "<[UNDEFINED]>"
+    );
+    assert_eq!(render!(in env, "{{ missing is undefined }}"), "true");
+    assert_eq!(render!(in env, "{{ missing == '' }}"), "false");
+    assert_eq!(render!(in env, "<{{ missing ~ '' }}>"), "<>");
+    assert_eq!(render!(in env, "<{{ none }}>"), "<none>");
+
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    assert_eq!(
+        env.render_str("{{ missing }}", ()).unwrap_err().kind(),
+        ErrorKind::UndefinedError
+    );
+}
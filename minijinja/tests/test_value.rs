@@ -127,6 +127,109 @@ fn test_safe_string_roundtrip() {
     assert_eq!(v.to_string(), v2.to_string());
 }
 
+#[test]
+fn test_mark_safe_and_into_unsafe() {
+    let v = Value::from("<b>HTML</b>").mark_safe();
+    assert!(v.is_safe());
+    assert_eq!(v.as_str(), Some("<b>HTML</b>"));
+
+    let v = v.into_unsafe();
+    assert!(!v.is_safe());
+    assert_eq!(v.as_str(), Some("<b>HTML</b>"));
+
+    let v = Value::from(42).mark_safe();
+    assert!(v.is_safe());
+    assert_eq!(v.as_str(), Some("42"));
+
+    let v = Value::from(42).into_unsafe();
+    assert_eq!(v, Value::from(42));
+
+    let env = minijinja::Environment::new();
+    let rv = env
+        .render_named_str(
+            "test.html",
+            "{{ a }}|{{ b }}",
+            minijinja::context! {
+                a => Value::from("<br>").mark_safe(),
+                b => Value::from_safe_string("<br>".into()).into_unsafe(),
+            },
+        )
+        .unwrap();
+    assert_eq!(rv, "<br>|&lt;br&gt;");
+}
+
+#[test]
+fn test_to_repr() {
+    assert_eq!(Value::from("foo").to_repr(), r#""foo""#);
+    assert_eq!(Value::from("say \"hi\"").to_repr(), r#""say \"hi\"""#);
+    assert_eq!(Value::from(vec![1, 2, 3]).to_repr(), "[1, 2, 3]");
+    assert_eq!(
+        Value::from_serializable(&serde_json::json!({
+            "a": {"b": ["c", 1.5, true, null]}
+        }))
+        .to_repr(),
+        r#"{"a": {"b": ["c", 1.5, true, none]}}"#
+    );
+    assert_eq!(Value::UNDEFINED.to_repr(), "undefined");
+    assert_eq!(Value::from("foo").to_string(), "foo");
+}
+
+#[test]
+fn test_contains() {
+    let s = Value::from("hello world");
+    assert!(s.contains(&Value::from("lo w")).unwrap());
+    assert!(!s.contains(&Value::from("xyz")).unwrap());
+    assert!(Value::from("a42b").contains(&Value::from(42)).unwrap());
+
+    let seq = Value::from(vec![1, 2, 3]);
+    assert!(seq.contains(&Value::from(2)).unwrap());
+    assert!(seq.contains(&Value::from(2.0)).unwrap());
+    assert!(!seq.contains(&Value::from(4)).unwrap());
+
+    let map = Value::from_iter([("foo", 1), ("bar", 2)]);
+    assert!(map.contains(&Value::from("foo")).unwrap());
+    assert!(!map.contains(&Value::from(1)).unwrap());
+
+    assert!(!Value::UNDEFINED.contains(&Value::from(1)).unwrap());
+    assert!(Value::from(42).contains(&Value::from(4)).is_err());
+}
+
+#[test]
+fn test_arithmetic() {
+    let one = Value::from(1);
+    let half = Value::from(0.5);
+    assert_eq!(one.add(&half).unwrap(), Value::from(1.5));
+    assert_eq!(one.add(&Value::from(2)).unwrap(), Value::from(3));
+    assert_eq!(one.sub(&half).unwrap(), Value::from(0.5));
+    assert_eq!(Value::from(3).mul(&half).unwrap(), Value::from(1.5));
+    assert_eq!(
+        Value::from(3).div(&Value::from(2)).unwrap(),
+        Value::from(1.5)
+    );
+    assert_eq!(
+        Value::from(7).floor_div(&Value::from(2)).unwrap(),
+        Value::from(3)
+    );
+    assert_eq!(Value::from(7).rem(&Value::from(4)).unwrap(), Value::from(3));
+    assert_eq!(
+        Value::from(2).pow(&Value::from(10)).unwrap(),
+        Value::from(1024)
+    );
+    assert_eq!(half.neg().unwrap(), Value::from(-0.5));
+    assert_eq!(
+        Value::from(i64::MAX).add(&Value::from(i64::MAX)).unwrap(),
+        Value::from(i64::MAX as i128 * 2)
+    );
+
+    let foo = Value::from("foo");
+    assert_eq!(foo.add(&Value::from("bar")).unwrap(), Value::from("foobar"));
+    assert_eq!(foo.concat(&Value::from(42)), Value::from("foo42"));
+    assert_eq!(Value::from(4).concat(&Value::from(2)), Value::from("42"));
+    assert!(foo.add(&one).is_err());
+    assert!(foo.neg().is_err());
+    assert!(one.floor_div(&Value::from(0)).is_err());
+}
+
 #[test]
 fn test_undefined_roundtrip() {
     let v = Value::UNDEFINED;
@@ -145,6 +248,86 @@ fn test_value_serialization() {
     );
 }
 
+#[test]
+#[cfg(feature = "json")]
+fn test_json_roundtrip() {
+    let source = r#"{"a":[1,2.5,{"b":null,"c":"d"}],"e":true}"#;
+    let value = Value::from_json_str(source).unwrap();
+    assert_eq!(
+        value.get_attr("a").unwrap().get_item_by_index(1).unwrap(),
+        Value::from(2.5)
+    );
+    assert_eq!(value.to_json_string().unwrap(), source);
+    assert_eq!(
+        Value::from_json_str(&value.to_json_string().unwrap()).unwrap(),
+        value
+    );
+
+    let err = Value::from_json_str("{invalid").unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::BadSerialization);
+
+    let obj = Value::from_seq_object(vec![1, 2, 3]);
+    assert_eq!(obj.to_json_string().unwrap(), "[1,2,3]");
+}
+
+#[test]
+#[cfg(feature = "json")]
+fn test_json_non_finite_floats() {
+    use minijinja::JsonFloatPolicy;
+
+    let value = Value::from_iter([Value::from(1.5), Value::from(f64::NAN)]);
+    let err = value.to_json_string().unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::BadSerialization);
+    assert_eq!(
+        value
+            .to_json_string_with_policy(JsonFloatPolicy::Null)
+            .unwrap(),
+        "[1.5,null]"
+    );
+
+    let nested = Value::from_struct_object({
+        struct Nested;
+        impl StructObject for Nested {
+            fn get_field(&self, name: &str) -> Option<Value> {
+                match name {
+                    "inf" => Some(Value::from(f64::INFINITY)),
+                    _ => None,
+                }
+            }
+            fn static_fields(&self) -> Option<&'static [&'static str]> {
+                Some(&["inf"][..])
+            }
+        }
+        Nested
+    });
+    assert!(nested.to_json_string().is_err());
+
+    let mut env = Environment::new();
+    env.add_template("data.json", "{{ value }}").unwrap();
+    let ctx = minijinja::context! { value };
+
+    let err = env
+        .render_str("{{ value|tojson }}", ctx.clone())
+        .unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::BadSerialization);
+    let err = env
+        .get_template("data.json")
+        .unwrap()
+        .render(ctx.clone())
+        .unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::BadSerialization);
+
+    env.set_json_float_policy(JsonFloatPolicy::Null);
+    assert_eq!(
+        env.render_str("{{ value|tojson }}", ctx.clone()).unwrap(),
+        "[1.5,null]"
+    );
+    assert_eq!(
+        env.get_template("data.json").unwrap().render(ctx).unwrap(),
+        "[1.5,null]"
+    );
+}
+
 #[test]
 fn test_float_to_string() {
     assert_eq!(Value::from(42.4242f64).to_string(), "42.4242");
@@ -162,6 +345,141 @@ fn test_value_by_index() {
     let val = Value::from(vec![1u32, 2, 3]);
     assert_eq!(val.get_item_by_index(0).unwrap(), Value::from(1));
     assert!(val.get_item_by_index(4).unwrap().is_undefined());
+
+    struct Squares;
+
+    impl SeqObject for Squares {
+        fn get_item(&self, idx: usize) -> Option<Value> {
+            (idx < 10).then(|| Value::from(idx * idx))
+        }
+
+        fn item_count(&self) -> usize {
+            10
+        }
+    }
+
+    let val = Value::from_seq_object(Squares);
+    assert_eq!(val.get_item_by_index(3).unwrap(), Value::from(9));
+    assert!(val.get_item_by_index(10).unwrap().is_undefined());
+
+    let map = Value::from_iter([(Value::from(1), Value::from("one"))]);
+    assert_eq!(map.get_item_by_index(1).unwrap(), Value::from("one"));
+    assert!(Value::from(42).get_item_by_index(0).unwrap().is_undefined());
+    assert!(Value::UNDEFINED.get_item_by_index(0).is_err());
+}
+
+#[test]
+fn test_get_attr() {
+    let map = Value::from_iter([("name", "Peter")]);
+    assert_eq!(map.get_attr("name").unwrap(), Value::from("Peter"));
+    assert!(map.get_attr("missing").unwrap().is_undefined());
+
+    struct User;
+
+    impl StructObject for User {
+        fn get_field(&self, name: &str) -> Option<Value> {
+            match name {
+                "name" => Some(Value::from("Sarah")),
+                _ => None,
+            }
+        }
+    }
+
+    let obj = Value::from_struct_object(User);
+    assert_eq!(obj.get_attr("name").unwrap(), Value::from("Sarah"));
+    assert!(obj.get_attr("missing").unwrap().is_undefined());
+
+    assert!(Value::from(42).get_attr("name").unwrap().is_undefined());
+    assert!(Value::UNDEFINED.get_attr("name").is_err());
+}
+
+#[test]
+#[cfg(feature = "builtins")]
+fn test_struct_object_field_count_hint() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct Dynamic {
+        fields_calls: AtomicUsize,
+    }
+
+    impl StructObject for Dynamic {
+        fn get_field(&self, name: &str) -> Option<Value> {
+            name.strip_prefix("field_")
+                .and_then(|x| x.parse::<usize>().ok())
+                .filter(|x| *x < 1000)
+                .map(Value::from)
+        }
+
+        fn fields(&self) -> Vec<Arc<str>> {
+            self.fields_calls.fetch_add(1, Ordering::Relaxed);
+            (0..1000)
+                .map(|x| Arc::from(format!("field_{}", x)))
+                .collect()
+        }
+
+        fn field_count(&self) -> usize {
+            1000
+        }
+    }
+
+    let obj = Arc::new(Dynamic {
+        fields_calls: AtomicUsize::new(0),
+    });
+    let value = Value::from_struct_object(obj.clone());
+    let env = Environment::new();
+    let rv = env
+        .render_str(
+            "{{ obj|length }} {{ 'yes' if obj else 'no' }} {{ obj.field_42 }}",
+            minijinja::context! { obj => value },
+        )
+        .unwrap();
+    assert_eq!(rv, "1000 yes 42");
+    assert_eq!(obj.fields_calls.load(Ordering::Relaxed), 0);
+}
+
+#[test]
+fn test_as_pairs() {
+    let value = Value::from_iter([("zebra", 1), ("apple", 2), ("mango", 3)]);
+    let keys = value
+        .as_pairs()
+        .unwrap()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>();
+    if cfg!(feature = "preserve_order") {
+        assert_eq!(keys, ["zebra=1", "apple=2", "mango=3"]);
+    } else {
+        assert_eq!(keys, ["apple=2", "mango=3", "zebra=1"]);
+    }
+
+    struct Point;
+
+    impl StructObject for Point {
+        fn get_field(&self, name: &str) -> Option<Value> {
+            match name {
+                "y" => Some(Value::from(2)),
+                "x" => Some(Value::from(1)),
+                _ => None,
+            }
+        }
+
+        fn static_fields(&self) -> Option<&'static [&'static str]> {
+            Some(&["y", "x"][..])
+        }
+    }
+
+    let value = Value::from_struct_object(Point);
+    let pairs = value.as_pairs().unwrap().collect::<Vec<_>>();
+    assert_eq!(
+        pairs,
+        [
+            (Value::from("y"), Value::from(2)),
+            (Value::from("x"), Value::from(1))
+        ]
+    );
+
+    assert!(Value::from(vec![1, 2]).as_pairs().is_none());
+    assert!(Value::from(42).as_pairs().is_none());
 }
 
 #[test]
@@ -260,6 +578,12 @@ fn test_seq_object_iteration_and_indexing() {
         point => Value::from_object(Point(1, 2, 3))
     );
     assert_snapshot!(rv, @"[1, 3, undefined]");
+
+    let rv = minijinja::render!(
+        "{{ [point[-1], point[-3], point[-4]] }}",
+        point => Value::from_object(Point(1, 2, 3))
+    );
+    assert_snapshot!(rv, @"[3, 1, undefined]");
 }
 
 #[test]
@@ -364,6 +688,110 @@ fn test_value_cmp() {
     assert_eq!(Value::UNDEFINED, Value::UNDEFINED);
 }
 
+#[test]
+fn test_value_try_iter() {
+    let seq = Value::from(vec![1, 2, 3]);
+    assert_eq!(
+        seq.try_iter().unwrap().collect::<Vec<_>>(),
+        vec![Value::from(1), Value::from(2), Value::from(3)]
+    );
+
+    let map = Value::from_iter([("a", 1), ("b", 2)]);
+    assert_eq!(
+        map.try_iter().unwrap().collect::<Vec<_>>(),
+        vec![Value::from("a"), Value::from("b")]
+    );
+
+    let string = Value::from("hä");
+    assert_eq!(
+        string.try_iter().unwrap().collect::<Vec<_>>(),
+        vec![Value::from("h"), Value::from("ä")]
+    );
+
+    assert_eq!(Value::UNDEFINED.try_iter().unwrap().count(), 0);
+    assert_eq!(Value::from(()).try_iter().unwrap().count(), 0);
+
+    let err = match Value::from(42).try_iter() {
+        Ok(_) => panic!("expected an error"),
+        Err(err) => err,
+    };
+    assert_eq!(err.kind(), minijinja::ErrorKind::InvalidOperation);
+    assert_eq!(err.detail(), Some("number is not iterable"));
+    assert!(Value::from(true).try_iter().is_err());
+}
+
+#[test]
+fn test_value_compare() {
+    use std::cmp::Ordering;
+
+    assert_eq!(
+        Value::from(1).compare(&Value::from(2.5)),
+        Some(Ordering::Less)
+    );
+    assert_eq!(
+        Value::from(3.0).compare(&Value::from(2)),
+        Some(Ordering::Greater)
+    );
+    assert_eq!(
+        Value::from(2).compare(&Value::from(2.0)),
+        Some(Ordering::Equal)
+    );
+    assert_eq!(
+        Value::from("apple").compare(&Value::from("banana")),
+        Some(Ordering::Less)
+    );
+    assert_eq!(
+        Value::from("b").compare(&Value::from("aaa")),
+        Some(Ordering::Greater)
+    );
+    assert_eq!(Value::from("1").compare(&Value::from(1)), None);
+
+    assert!(Value::from(1).eq_value(&Value::from(1.0)));
+    assert!(Value::from("a").eq_value(&Value::from("a")));
+    assert!(!Value::from("1").eq_value(&Value::from(1)));
+}
+
+#[test]
+#[cfg(feature = "builtins")]
+fn test_object_custom_cmp() {
+    use std::cmp::Ordering;
+
+    #[derive(Debug)]
+    struct Version(u32, u32);
+
+    impl fmt::Display for Version {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}.{}", self.0, self.1)
+        }
+    }
+
+    impl Object for Version {
+        fn custom_cmp(&self, other: &Value) -> Option<Ordering> {
+            let other = other.downcast_object_ref::<Version>()?;
+            Some((self.0, self.1).cmp(&(other.0, other.1)))
+        }
+    }
+
+    let versions = vec![
+        Value::from_object(Version(1, 10)),
+        Value::from_object(Version(0, 9)),
+        Value::from_object(Version(1, 2)),
+    ];
+    let rv = minijinja::render!(
+        "{{ versions|sort|join(', ') }}|{{ versions[0] > versions[2] }}",
+        versions => versions.clone()
+    );
+    assert_snapshot!(rv, @"0.9, 1.2, 1.10|true");
+
+    assert_eq!(versions[1].compare(&versions[2]), Some(Ordering::Less));
+    let mut sorted = versions;
+    sorted.sort_by(|a, b| a.compare(b).unwrap());
+    assert_eq!(
+        sorted.iter().map(|x| x.to_string()).collect::<Vec<_>>(),
+        vec!["0.9", "1.2", "1.10"]
+    );
+}
+
 #[test]
 fn test_call_kwargs() {
     let mut env = Environment::new();
@@ -387,6 +815,32 @@ fn test_kwargs_error() {
     assert_eq!(bar.detail(), Some("missing keyword argument 'bar'"));
 }
 
+#[test]
+fn test_kwargs_into_map() {
+    let mut env = Environment::new();
+    env.add_function(
+        "link",
+        |url: &str, kwargs: Kwargs| -> Result<String, Error> {
+            let title: String = kwargs.get("title")?;
+            let _peeked: Option<String> = kwargs.peek("target")?;
+            let attrs = kwargs.into_map();
+            Ok(format!("{} {} {}", url, title, attrs))
+        },
+    );
+    let rv = env
+        .render_str(
+            "{{ link('/', title='Home', class='nav', target='_top') }}",
+            (),
+        )
+        .unwrap();
+    assert_eq!(rv, r#"/ Home {"class": "nav", "target": "_top"}"#);
+
+    let kwargs = Kwargs::from_iter([("a", Value::from(1))]);
+    let map = kwargs.into_map();
+    assert!(!map.is_kwargs());
+    assert_eq!(map.get_attr("a").unwrap(), Value::from(1));
+}
+
 #[test]
 fn test_return_none() {
     let env = Environment::empty();
@@ -430,6 +884,48 @@ fn test_rest_args() {
     );
 }
 
+#[test]
+fn test_typed_rest_args() {
+    fn concat(val: String, rest: Rest<String>) -> String {
+        rest.iter().fold(val, |a, b| a + b)
+    }
+
+    fn shout(rest: Rest<&str>) -> String {
+        rest.join(" ").to_uppercase()
+    }
+
+    fn total(rest: Rest<i64>) -> i64 {
+        rest.iter().sum()
+    }
+
+    let mut env = Environment::new();
+    env.add_filter("concat", concat);
+    env.add_function("shout", shout);
+    env.add_function("total", total);
+    let state = env.empty_state();
+
+    assert_eq!(
+        state.apply_filter("concat", args!("a", "b", 42)).unwrap(),
+        Value::from("ab42")
+    );
+    assert_eq!(
+        env.render_str("{{ shout('hello', 'world') }}", ()).unwrap(),
+        "HELLO WORLD"
+    );
+    assert_eq!(env.render_str("{{ total(1, 2, 3) }}", ()).unwrap(), "6");
+
+    let err = env
+        .render_str("{{ shout('hello', 42, true) }}", ())
+        .unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::InvalidOperation);
+    assert_eq!(err.detail(), Some("argument 2: value is not a string"));
+    let err = env.render_str("{{ total(1, 2, 'x') }}", ()).unwrap_err();
+    assert_eq!(
+        err.detail(),
+        Some("argument 3: cannot convert string to i64")
+    );
+}
+
 #[test]
 fn test_optional_args() {
     fn add(val: u32, a: u32, b: Option<u32>) -> Result<u32, Error> {
@@ -511,6 +1007,72 @@ fn test_seq_object_borrow() {
     );
 }
 
+#[test]
+fn test_object_snapshot() {
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug)]
+    struct Namespace {
+        values: Mutex<BTreeMap<String, Value>>,
+        copy_on_scope: bool,
+    }
+
+    impl fmt::Display for Namespace {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "<namespace>")
+        }
+    }
+
+    impl Object for Namespace {
+        fn kind(&self) -> ObjectKind<'_> {
+            ObjectKind::Struct(self)
+        }
+
+        fn call_method(
+            &self,
+            _state: &minijinja::State,
+            name: &str,
+            args: &[Value],
+        ) -> Result<Value, Error> {
+            assert_eq!(name, "set");
+            let (key, value): (String, Value) = minijinja::value::from_args(args)?;
+            self.values.lock().unwrap().insert(key, value);
+            Ok(Value::from(()))
+        }
+
+        fn snapshot(&self) -> Option<Arc<dyn Object>> {
+            self.copy_on_scope.then(|| {
+                Arc::new(Namespace {
+                    values: Mutex::new(self.values.lock().unwrap().clone()),
+                    copy_on_scope: true,
+                }) as Arc<dyn Object>
+            })
+        }
+    }
+
+    impl StructObject for Namespace {
+        fn get_field(&self, name: &str) -> Option<Value> {
+            self.values.lock().unwrap().get(name).cloned()
+        }
+    }
+
+    let env = Environment::new();
+    let source = "{% do ns.set('count', 1) %}\
+        {% with ns = ns %}{% do ns.set('count', 2) %}inner={{ ns.count }}{% endwith %} \
+        outer={{ ns.count }}";
+    for (copy_on_scope, expected) in [(true, "inner=2 outer=1"), (false, "inner=2 outer=2")] {
+        let ns = Value::from_object(Namespace {
+            values: Mutex::default(),
+            copy_on_scope,
+        });
+        assert_eq!(
+            env.render_str(source, minijinja::context! { ns }).unwrap(),
+            expected
+        );
+    }
+}
+
 #[test]
 fn test_complex_key() {
     let value = Value::from_iter([
@@ -528,6 +1090,58 @@ fn test_complex_key() {
     );
 }
 
+#[test]
+fn test_value_as_hash_map_key() {
+    use std::collections::HashMap;
+
+    struct Point;
+
+    impl StructObject for Point {
+        fn get_field(&self, name: &str) -> Option<Value> {
+            match name {
+                "x" => Some(Value::from(1)),
+                "y" => Some(Value::from(2)),
+                _ => None,
+            }
+        }
+
+        fn static_fields(&self) -> Option<&'static [&'static str]> {
+            Some(&["y", "x"][..])
+        }
+    }
+
+    struct Pair;
+
+    impl SeqObject for Pair {
+        fn get_item(&self, idx: usize) -> Option<Value> {
+            [Value::from("a"), Value::from("b")].get(idx).cloned()
+        }
+
+        fn item_count(&self) -> usize {
+            2
+        }
+    }
+
+    let mut map = HashMap::new();
+    map.insert(Value::from(1), "one");
+    map.insert(Value::from("1"), "string one");
+    map.insert(Value::from(vec!["a", "b"]), "pair");
+    map.insert(Value::from_iter([("x", 1), ("y", 2)]), "point");
+
+    assert_eq!(map.get(&Value::from(1u64)), Some(&"one"));
+    assert_eq!(map.get(&Value::from(1.0)), Some(&"one"));
+    assert_eq!(map.get(&Value::from(true)), Some(&"one"));
+    assert_eq!(map.get(&Value::from(1.5)), None);
+    assert_eq!(map.get(&Value::from("1")), Some(&"string one"));
+    assert_eq!(map.get(&Value::from_seq_object(Pair)), Some(&"pair"));
+    assert_eq!(
+        map.get(&Value::from_iter([("y", 2), ("x", 1)])),
+        Some(&"point")
+    );
+    assert_eq!(map.get(&Value::from_struct_object(Point)), Some(&"point"));
+    assert_eq!(map.get(&Value::from_iter([("x", 1), ("y", 3)])), None);
+}
+
 #[test]
 #[cfg(feature = "deserialization")]
 fn test_deserialize() {
@@ -570,3 +1184,267 @@ fn test_via_deserialize() {
     let rv = state.apply_filter("foo", args![point_value]).unwrap();
     assert_eq!(rv.to_string(), "42, -23");
 }
+
+#[test]
+fn test_try_into_string() {
+    assert_eq!(Value::from("foo").try_into_string().unwrap(), "foo");
+    assert_eq!(
+        Value::from_safe_string("<b>".into())
+            .try_into_string()
+            .unwrap(),
+        "<b>"
+    );
+
+    let err = Value::from(42).try_into_string().unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::InvalidOperation);
+    assert_eq!(err.detail(), Some("value of type number is not a string"));
+    assert!(Value::UNDEFINED.try_into_string().is_err());
+    assert!(Value::from(vec!["a"]).try_into_string().is_err());
+}
+
+#[test]
+fn test_object_get_value_fallback() {
+    #[derive(Debug)]
+    struct Proxy;
+
+    impl fmt::Display for Proxy {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "<proxy>")
+        }
+    }
+
+    impl Object for Proxy {
+        fn kind(&self) -> ObjectKind<'_> {
+            ObjectKind::Struct(self)
+        }
+
+        fn get_value_fallback(&self, key: &Value) -> Option<Value> {
+            match key.as_str() {
+                Some(key) => Some(Value::from(format!("dynamic-{key}"))),
+                None if key.is_number() => Some(Value::from(key.to_string().repeat(2))),
+                None => None,
+            }
+        }
+    }
+
+    impl StructObject for Proxy {
+        fn get_field(&self, name: &str) -> Option<Value> {
+            match name {
+                "fixed" => Some(Value::from("static")),
+                _ => None,
+            }
+        }
+    }
+
+    let proxy = Value::from_object(Proxy);
+    assert_eq!(proxy.get_attr("fixed").unwrap(), Value::from("static"));
+    assert_eq!(
+        proxy.get_attr("anything").unwrap(),
+        Value::from("dynamic-anything")
+    );
+    assert_eq!(
+        proxy.get_item(&Value::from(42)).unwrap(),
+        Value::from("4242")
+    );
+    assert!(proxy.get_item(&Value::from(())).unwrap().is_undefined());
+
+    let env = Environment::new();
+    let rv = env
+        .render_str(
+            "{{ proxy.fixed }}|{{ proxy.foo }}|{{ proxy['bar'] }}|{{ proxy[1] }}",
+            minijinja::context! { proxy },
+        )
+        .unwrap();
+    assert_eq!(rv, "static|dynamic-foo|dynamic-bar|11");
+}
+
+#[test]
+fn test_as_usize() {
+    assert_eq!(Value::from(42).as_usize(), Some(42));
+    assert_eq!(Value::from(0u64).as_usize(), Some(0));
+    assert_eq!(Value::from(7i128).as_usize(), Some(7));
+    assert_eq!(Value::from(3.0).as_usize(), Some(3));
+    assert_eq!(Value::from(-1).as_usize(), None);
+    assert_eq!(Value::from(-2.0).as_usize(), None);
+    assert_eq!(Value::from(1.5).as_usize(), None);
+    assert_eq!(Value::from(f64::NAN).as_usize(), None);
+    assert_eq!(Value::from(u128::MAX).as_u64(), None);
+    assert_eq!(Value::from(18446744073709551616.0f64).as_u64(), None);
+    assert_eq!(Value::from(true).as_usize(), None);
+    assert_eq!(Value::from("1").as_usize(), None);
+}
+
+#[test]
+fn test_make_iterable() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let data = Arc::new(vec!["a", "b", "c"]);
+    let calls = Arc::new(AtomicUsize::new(0));
+    let value = Value::make_iterable({
+        let data = data.clone();
+        let calls = calls.clone();
+        move || {
+            calls.fetch_add(1, Ordering::Relaxed);
+            let data = data.clone();
+            (0..data.len()).map(move |idx| Value::from(data[idx]))
+        }
+    });
+
+    let first = value.try_iter().unwrap().collect::<Vec<_>>();
+    let second = value.try_iter().unwrap().collect::<Vec<_>>();
+    assert_eq!(first, second);
+    assert_eq!(
+        first,
+        vec![Value::from("a"), Value::from("b"), Value::from("c")]
+    );
+    assert_eq!(calls.load(Ordering::Relaxed), 2);
+
+    // indexing and the length re-run the maker
+    assert_eq!(value.len(), Some(3));
+    assert_eq!(value.get_item_by_index(1).unwrap(), Value::from("b"));
+    assert_eq!(value.get_item_by_index(2).unwrap(), Value::from("c"));
+    assert_eq!(calls.load(Ordering::Relaxed), 5);
+    assert_eq!(value.to_string(), r#"["a", "b", "c"]"#);
+    assert_eq!(serde_json::to_string(&value).unwrap(), r#"["a","b","c"]"#);
+
+    // changes to the source are picked up by indexing and iteration alike
+    let count = Arc::new(AtomicUsize::new(2));
+    let growing = Value::make_iterable({
+        let count = count.clone();
+        move || (0..count.load(Ordering::Relaxed)).map(Value::from)
+    });
+    assert_eq!(growing.len(), Some(2));
+    count.store(3, Ordering::Relaxed);
+    assert_eq!(growing.len(), Some(3));
+    assert_eq!(growing.get_item_by_index(2).unwrap(), Value::from(2));
+    assert_eq!(growing.try_iter().unwrap().count(), 3);
+
+    let env = Environment::new();
+    let rv = env
+        .render_str(
+            "{% for x in seq %}{{ x }}{% endfor %}|{% for x in seq %}\
+             {{ loop.index }}{{ loop.length is undefined }}{% endfor %}",
+            minijinja::context! { seq => value },
+        )
+        .unwrap();
+    assert_eq!(rv, "abc|1true2true3true");
+}
+
+#[test]
+#[cfg(feature = "builtins")]
+fn test_struct_object_as_map() {
+    struct Config {
+        name: &'static str,
+        retries: u32,
+    }
+
+    impl StructObject for Config {
+        fn get_field(&self, name: &str) -> Option<Value> {
+            match name {
+                "name" => Some(Value::from(self.name)),
+                "retries" => Some(Value::from(self.retries)),
+                "describe" => {
+                    let name = self.name;
+                    Some(Value::from_function(move |prefix: &str| {
+                        format!("{prefix}{name}")
+                    }))
+                }
+                _ => None,
+            }
+        }
+
+        fn static_fields(&self) -> Option<&'static [&'static str]> {
+            Some(&["name", "retries"][..])
+        }
+    }
+
+    let config = Value::from_struct_object(Config {
+        name: "app",
+        retries: 3,
+    });
+    assert_eq!(config.len(), Some(2));
+
+    let env = Environment::new();
+    let rv = env
+        .render_str(
+            "{% for key in config %}{{ key }}={{ config[key] }};{% endfor %}\
+             |{% for k, v in config|items %}{{ k }}:{{ v }};{% endfor %}\
+             |{{ config.describe('name: ') }}|{{ config|length }}|{{ config.missing is undefined }}",
+            minijinja::context! { config },
+        )
+        .unwrap();
+    assert_eq!(
+        rv,
+        "name=app;retries=3;|name:app;retries:3;|name: app|2|true"
+    );
+
+    let err = env
+        .render_str("{{ config.nope() }}", minijinja::context! { config })
+        .unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::UnknownMethod);
+}
+
+#[test]
+fn test_deep_clone() {
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct SharedList(Mutex<Vec<Value>>);
+
+    impl SeqObject for SharedList {
+        fn get_item(&self, idx: usize) -> Option<Value> {
+            self.0.lock().unwrap().get(idx).cloned()
+        }
+
+        fn item_count(&self) -> usize {
+            self.0.lock().unwrap().len()
+        }
+    }
+
+    struct Settings;
+
+    impl StructObject for Settings {
+        fn get_field(&self, name: &str) -> Option<Value> {
+            match name {
+                "debug" => Some(Value::from(true)),
+                _ => None,
+            }
+        }
+
+        fn static_fields(&self) -> Option<&'static [&'static str]> {
+            Some(&["debug"][..])
+        }
+    }
+
+    let list = std::sync::Arc::new(SharedList::default());
+    list.0.lock().unwrap().push(Value::from(1));
+    let original = Value::from_iter([
+        ("list", Value::from_seq_object(list.clone())),
+        ("settings", Value::from_struct_object(Settings)),
+        (
+            "nested",
+            Value::from(vec![Value::from_seq_object(list.clone())]),
+        ),
+    ]);
+    let shallow = original.clone();
+    let deep = original.deep_clone();
+
+    list.0.lock().unwrap().push(Value::from(2));
+
+    assert_eq!(shallow.get_attr("list").unwrap().to_string(), "[1, 2]");
+    assert_eq!(deep.get_attr("list").unwrap().to_string(), "[1]");
+    assert_eq!(
+        deep.get_attr("nested")
+            .unwrap()
+            .get_item_by_index(0)
+            .unwrap()
+            .to_string(),
+        "[1]"
+    );
+    assert!(deep.get_attr("list").unwrap().as_object().is_none());
+
+    let settings = deep.get_attr("settings").unwrap();
+    assert!(settings.as_object().is_none());
+    assert_eq!(settings.get_attr("debug").unwrap(), Value::from(true));
+}